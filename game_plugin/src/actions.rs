@@ -1,131 +1,368 @@
+use crate::player::{Player, PlayerCamera};
+use crate::settings::Settings;
 use crate::GameState;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
+use bevy::render::camera::Camera;
+use serde::{Deserialize, Serialize};
 
 pub struct ActionsPlugin;
 
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.init_resource::<Actions>().add_system_set(
-            SystemSet::on_update(GameState::Playing).with_system(set_movement_actions.system()),
-        );
+        app.init_resource::<Actions>()
+            .init_resource::<ConnectedGamepads>()
+            .add_system(track_gamepads.system())
+            // pausing and resuming happen in different states
+            .add_system(set_pause_action.system().label(ActionsSystemLabels::Input))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(
+                        set_movement_actions
+                            .system()
+                            .label(ActionsSystemLabels::Input),
+                    )
+                    .with_system(
+                        set_field_of_view_actions
+                            .system()
+                            .label(ActionsSystemLabels::Input),
+                    )
+                    .with_system(set_aim_actions.system().label(ActionsSystemLabels::Input)),
+            );
     }
 }
 
-#[derive(Default)]
+// Systems reading live input are labeled `Input`, replayed input overwrites them as `Replay`
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub enum ActionsSystemLabels {
+    Input,
+    Replay,
+}
+
 pub struct Actions {
     pub player_movement: Option<Vec2>,
+    pub field_of_view_zoom: Option<f32>,
+    // World space direction from the player to where they are looking, kept while there is no new aim
+    pub aim_direction: Vec2,
+    // Consumed by whoever handles it, since the state stage can run twice in a frame
+    pub pause: bool,
 }
 
-enum GameControl {
+impl Default for Actions {
+    fn default() -> Self {
+        Actions {
+            player_movement: None,
+            field_of_view_zoom: None,
+            aim_direction: Vec2::new(0., 1.),
+            pause: false,
+        }
+    }
+}
+
+const STICK_DEAD_ZONE: f32 = 0.2;
+// Mouse wheel lines per second while a zoom button is held
+const ZOOM_BUTTON_SPEED: f32 = 8.;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum GameControl {
     Up,
     Down,
     Left,
     Right,
+    // same direction as scrolling up
+    ZoomIn,
+    ZoomOut,
+    Pause,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum InputButton {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
 }
 
-impl GameControl {
-    fn just_released(&self, keyboard_input: &Res<Input<KeyCode>>) -> bool {
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+impl Stick {
+    fn axes(&self) -> (GamepadAxisType, GamepadAxisType) {
         match self {
-            GameControl::Up => {
-                keyboard_input.just_released(KeyCode::W)
-                    || keyboard_input.just_released(KeyCode::Up)
-            }
-            GameControl::Down => {
-                keyboard_input.just_released(KeyCode::S)
-                    || keyboard_input.just_released(KeyCode::Down)
-            }
-            GameControl::Left => {
-                keyboard_input.just_released(KeyCode::A)
-                    || keyboard_input.just_released(KeyCode::Left)
-            }
-            GameControl::Right => {
-                keyboard_input.just_released(KeyCode::D)
-                    || keyboard_input.just_released(KeyCode::Right)
-            }
+            Stick::Left => (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            Stick::Right => (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
         }
     }
+}
 
-    fn pressed(&self, keyboard_input: &Res<Input<KeyCode>>) -> bool {
-        match self {
-            GameControl::Up => {
-                keyboard_input.pressed(KeyCode::W) || keyboard_input.pressed(KeyCode::Up)
-            }
-            GameControl::Down => {
-                keyboard_input.pressed(KeyCode::S) || keyboard_input.pressed(KeyCode::Down)
-            }
-            GameControl::Left => {
-                keyboard_input.pressed(KeyCode::A) || keyboard_input.pressed(KeyCode::Left)
-            }
-            GameControl::Right => {
-                keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right)
-            }
+// Which inputs trigger which control. A control can have any number of buttons,
+// the mouse wheel always zooms and the cursor always aims.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bindings {
+    pub buttons: Vec<(GameControl, InputButton)>,
+    pub movement_stick: Stick,
+    pub aim_stick: Stick,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            buttons: vec![
+                (GameControl::Up, InputButton::Key(KeyCode::W)),
+                (GameControl::Down, InputButton::Key(KeyCode::S)),
+                (GameControl::Left, InputButton::Key(KeyCode::A)),
+                (GameControl::Right, InputButton::Key(KeyCode::D)),
+                (GameControl::Up, InputButton::Key(KeyCode::Up)),
+                (GameControl::Down, InputButton::Key(KeyCode::Down)),
+                (GameControl::Left, InputButton::Key(KeyCode::Left)),
+                (GameControl::Right, InputButton::Key(KeyCode::Right)),
+                (
+                    GameControl::Up,
+                    InputButton::Gamepad(GamepadButtonType::DPadUp),
+                ),
+                (
+                    GameControl::Down,
+                    InputButton::Gamepad(GamepadButtonType::DPadDown),
+                ),
+                (
+                    GameControl::Left,
+                    InputButton::Gamepad(GamepadButtonType::DPadLeft),
+                ),
+                (
+                    GameControl::Right,
+                    InputButton::Gamepad(GamepadButtonType::DPadRight),
+                ),
+                (GameControl::ZoomIn, InputButton::Key(KeyCode::E)),
+                (
+                    GameControl::ZoomIn,
+                    InputButton::Gamepad(GamepadButtonType::RightTrigger),
+                ),
+                (GameControl::ZoomOut, InputButton::Key(KeyCode::Q)),
+                (
+                    GameControl::ZoomOut,
+                    InputButton::Gamepad(GamepadButtonType::LeftTrigger),
+                ),
+                (GameControl::Pause, InputButton::Key(KeyCode::Escape)),
+                (
+                    GameControl::Pause,
+                    InputButton::Gamepad(GamepadButtonType::Start),
+                ),
+            ],
+            movement_stick: Stick::Left,
+            aim_stick: Stick::Right,
+        }
+    }
+}
+
+impl Bindings {
+    // The first keyboard key bound to the control, which is the one shown and changed in the settings
+    pub fn key(&self, control: GameControl) -> Option<KeyCode> {
+        self.buttons
+            .iter()
+            .find_map(|(bound, button)| match button {
+                InputButton::Key(key) if *bound == control => Some(*key),
+                _ => None,
+            })
+    }
+
+    // A key only ever triggers one control, so it is taken away from any other control
+    pub fn bind_key(&mut self, control: GameControl, key: KeyCode) {
+        self.buttons
+            .retain(|(bound, button)| *bound == control || *button != InputButton::Key(key));
+        let existing = self
+            .buttons
+            .iter_mut()
+            .find(|(bound, button)| *bound == control && matches!(button, InputButton::Key(_)));
+        if let Some((_, button)) = existing {
+            *button = InputButton::Key(key);
+        } else {
+            self.buttons.push((control, InputButton::Key(key)));
         }
     }
 
-    fn just_pressed(&self, keyboard_input: &Res<Input<KeyCode>>) -> bool {
+    fn buttons(&self, control: GameControl) -> impl Iterator<Item = &InputButton> {
+        self.buttons
+            .iter()
+            .filter(move |(bound, _)| *bound == control)
+            .map(|(_, button)| button)
+    }
+}
+
+#[derive(Default)]
+pub struct ConnectedGamepads(Vec<Gamepad>);
+
+#[derive(Clone, Copy)]
+enum Press {
+    Pressed,
+    JustPressed,
+    JustReleased,
+}
+
+impl Press {
+    fn check<T: Copy + Eq + std::hash::Hash>(&self, input: &Input<T>, value: T) -> bool {
         match self {
-            GameControl::Up => {
-                keyboard_input.just_pressed(KeyCode::W) || keyboard_input.just_pressed(KeyCode::Up)
-            }
-            GameControl::Down => {
-                keyboard_input.just_pressed(KeyCode::S)
-                    || keyboard_input.just_pressed(KeyCode::Down)
-            }
-            GameControl::Left => {
-                keyboard_input.just_pressed(KeyCode::A)
-                    || keyboard_input.just_pressed(KeyCode::Left)
+            Press::Pressed => input.pressed(value),
+            Press::JustPressed => input.just_pressed(value),
+            Press::JustReleased => input.just_released(value),
+        }
+    }
+}
+
+// Everything needed to tell whether a control is pressed, independent of the device
+struct ControlInput<'a> {
+    bindings: &'a Bindings,
+    keyboard: &'a Input<KeyCode>,
+    mouse: &'a Input<MouseButton>,
+    gamepad_buttons: &'a Input<GamepadButton>,
+    gamepads: &'a ConnectedGamepads,
+}
+
+impl<'a> ControlInput<'a> {
+    fn is(&self, control: GameControl, press: Press) -> bool {
+        self.bindings.buttons(control).any(|button| match *button {
+            InputButton::Key(key) => press.check(self.keyboard, key),
+            InputButton::Mouse(mouse_button) => press.check(self.mouse, mouse_button),
+            InputButton::Gamepad(button_type) => self.gamepads.0.iter().any(|gamepad| {
+                press.check(self.gamepad_buttons, GamepadButton(*gamepad, button_type))
+            }),
+        })
+    }
+
+    fn pressed(&self, control: GameControl) -> bool {
+        self.is(control, Press::Pressed)
+    }
+
+    fn just_pressed(&self, control: GameControl) -> bool {
+        self.is(control, Press::JustPressed)
+    }
+
+    fn just_released(&self, control: GameControl) -> bool {
+        self.is(control, Press::JustReleased)
+    }
+}
+
+// The deflection of the stick on the first gamepad that is outside the dead zone
+fn stick_position(
+    stick: Stick,
+    gamepads: &ConnectedGamepads,
+    axes: &Axis<GamepadAxis>,
+) -> Option<Vec2> {
+    let (x_axis, y_axis) = stick.axes();
+    gamepads.0.iter().find_map(|gamepad| {
+        let position = Vec2::new(
+            axes.get(GamepadAxis(*gamepad, x_axis)).unwrap_or(0.),
+            axes.get(GamepadAxis(*gamepad, y_axis)).unwrap_or(0.),
+        );
+        if position.length() > STICK_DEAD_ZONE {
+            Some(position)
+        } else {
+            None
+        }
+    })
+}
+
+fn track_gamepads(
+    mut gamepads: ResMut<ConnectedGamepads>,
+    mut gamepad_events: EventReader<GamepadEvent>,
+) {
+    for GamepadEvent(gamepad, event_type) in gamepad_events.iter() {
+        match event_type {
+            GamepadEventType::Connected => {
+                info!("Gamepad {:?} connected", gamepad);
+                gamepads.0.push(*gamepad);
             }
-            GameControl::Right => {
-                keyboard_input.just_pressed(KeyCode::D)
-                    || keyboard_input.just_pressed(KeyCode::Right)
+            GamepadEventType::Disconnected => {
+                gamepads.0.retain(|connected| connected != gamepad);
             }
+            _ => (),
         }
     }
 }
 
-fn set_movement_actions(mut actions: ResMut<Actions>, keyboard_input: Res<Input<KeyCode>>) {
-    if GameControl::Up.just_released(&keyboard_input)
-        || GameControl::Up.pressed(&keyboard_input)
-        || GameControl::Left.just_released(&keyboard_input)
-        || GameControl::Left.pressed(&keyboard_input)
-        || GameControl::Down.just_released(&keyboard_input)
-        || GameControl::Down.pressed(&keyboard_input)
-        || GameControl::Right.just_released(&keyboard_input)
-        || GameControl::Right.pressed(&keyboard_input)
+fn set_pause_action(
+    mut actions: ResMut<Actions>,
+    settings: Res<Settings>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<ConnectedGamepads>,
+) {
+    let input = ControlInput {
+        bindings: &settings.bindings,
+        keyboard: &keyboard_input,
+        mouse: &mouse_input,
+        gamepad_buttons: &gamepad_buttons,
+        gamepads: &gamepads,
+    };
+    actions.pause = input.just_pressed(GameControl::Pause);
+}
+
+fn set_movement_actions(
+    mut actions: ResMut<Actions>,
+    settings: Res<Settings>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<ConnectedGamepads>,
+) {
+    if let Some(stick) = stick_position(settings.bindings.movement_stick, &gamepads, &gamepad_axes)
+    {
+        actions.player_movement = Some(if stick.length() > 1. {
+            stick.normalize()
+        } else {
+            stick
+        });
+        return;
+    }
+    let input = ControlInput {
+        bindings: &settings.bindings,
+        keyboard: &keyboard_input,
+        mouse: &mouse_input,
+        gamepad_buttons: &gamepad_buttons,
+        gamepads: &gamepads,
+    };
+    if input.just_released(GameControl::Up)
+        || input.pressed(GameControl::Up)
+        || input.just_released(GameControl::Left)
+        || input.pressed(GameControl::Left)
+        || input.just_released(GameControl::Down)
+        || input.pressed(GameControl::Down)
+        || input.just_released(GameControl::Right)
+        || input.pressed(GameControl::Right)
     {
         let mut player_movement = Vec2::ZERO;
 
-        if GameControl::Up.just_released(&keyboard_input)
-            || GameControl::Down.just_released(&keyboard_input)
-        {
-            if GameControl::Up.pressed(&keyboard_input) {
+        if input.just_released(GameControl::Up) || input.just_released(GameControl::Down) {
+            if input.pressed(GameControl::Up) {
                 player_movement.y = 1.;
-            } else if GameControl::Down.pressed(&keyboard_input) {
+            } else if input.pressed(GameControl::Down) {
                 player_movement.y = -1.;
             } else {
                 player_movement.y = 0.;
             }
-        } else if GameControl::Up.just_pressed(&keyboard_input) {
+        } else if input.just_pressed(GameControl::Up) {
             player_movement.y = 1.;
-        } else if GameControl::Down.just_pressed(&keyboard_input) {
+        } else if input.just_pressed(GameControl::Down) {
             player_movement.y = -1.;
         } else {
             player_movement.y = actions.player_movement.unwrap_or(Vec2::ZERO).y;
         }
 
-        if GameControl::Right.just_released(&keyboard_input)
-            || GameControl::Left.just_released(&keyboard_input)
-        {
-            if GameControl::Right.pressed(&keyboard_input) {
+        if input.just_released(GameControl::Right) || input.just_released(GameControl::Left) {
+            if input.pressed(GameControl::Right) {
                 player_movement.x = 1.;
-            } else if GameControl::Left.pressed(&keyboard_input) {
+            } else if input.pressed(GameControl::Left) {
                 player_movement.x = -1.;
             } else {
                 player_movement.x = 0.;
             }
-        } else if GameControl::Right.just_pressed(&keyboard_input) {
+        } else if input.just_pressed(GameControl::Right) {
             player_movement.x = 1.;
-        } else if GameControl::Left.just_pressed(&keyboard_input) {
+        } else if input.just_pressed(GameControl::Left) {
             player_movement.x = -1.;
         } else {
             player_movement.x = actions.player_movement.unwrap_or(Vec2::ZERO).x;
@@ -139,3 +376,78 @@ fn set_movement_actions(mut actions: ResMut<Actions>, keyboard_input: Res<Input<
         actions.player_movement = None;
     }
 }
+
+fn set_field_of_view_actions(
+    mut actions: ResMut<Actions>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<ConnectedGamepads>,
+) {
+    let input = ControlInput {
+        bindings: &settings.bindings,
+        keyboard: &keyboard_input,
+        mouse: &mouse_input,
+        gamepad_buttons: &gamepad_buttons,
+        gamepads: &gamepads,
+    };
+    let mut zoom: f32 = mouse_wheel_events
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // roughly one line per 20 pixels on touchpads
+            MouseScrollUnit::Pixel => event.y / 20.,
+        })
+        .sum();
+    if input.pressed(GameControl::ZoomIn) {
+        zoom += ZOOM_BUTTON_SPEED * time.delta_seconds();
+    }
+    if input.pressed(GameControl::ZoomOut) {
+        zoom -= ZOOM_BUTTON_SPEED * time.delta_seconds();
+    }
+    actions.field_of_view_zoom = if zoom != 0. { Some(zoom) } else { None };
+}
+
+// Projects a cursor position in window pixels through the camera into the world
+fn cursor_to_world(cursor: Vec2, window_size: Vec2, camera_transform: &Transform) -> Vec2 {
+    camera_transform
+        .mul_vec3((cursor - window_size / 2.).extend(0.))
+        .truncate()
+}
+
+fn set_aim_actions(
+    mut actions: ResMut<Actions>,
+    settings: Res<Settings>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<ConnectedGamepads>,
+    windows: Res<Windows>,
+    camera_query: Query<(&Camera, &Transform), With<PlayerCamera>>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    if let Some(stick) = stick_position(settings.bindings.aim_stick, &gamepads, &gamepad_axes) {
+        actions.aim_direction = stick.normalize();
+        return;
+    }
+    let player_position = if let Ok(transform) = player_query.single() {
+        transform.translation.truncate()
+    } else {
+        return;
+    };
+    for (camera, camera_transform) in camera_query.iter() {
+        let window = if let Some(window) = windows.get(camera.window) {
+            window
+        } else {
+            continue;
+        };
+        if let Some(cursor) = window.cursor_position() {
+            let window_size = Vec2::new(window.width() as f32, window.height() as f32);
+            let aim = cursor_to_world(cursor, window_size, camera_transform) - player_position;
+            if aim.length() > f32::EPSILON {
+                actions.aim_direction = aim.normalize();
+            }
+        }
+    }
+}
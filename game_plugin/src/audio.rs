@@ -1,9 +1,12 @@
+use crate::enemies::{EnemyChaseEvent, EnemyRepelledEvent};
 use crate::loading::AudioAssets;
 use crate::player::{BefriendEvent, DyingEvent, LevelUpEvent, NopeEvent, WonEvent};
+use crate::rng::AudioRng;
+use crate::settings::Settings;
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_kira_audio::{Audio, AudioChannel, AudioPlugin};
-use rand::random;
+use rand::Rng;
 
 pub struct InternalAudioPlugin;
 
@@ -14,6 +17,7 @@ impl Plugin for InternalAudioPlugin {
             effects: AudioChannel::new("effects".to_owned()),
         })
         .add_plugin(AudioPlugin)
+        .add_system(apply_volumes.system())
         .add_system_set(
             SystemSet::on_enter(GameState::RenderBackground).with_system(start_audio.system()),
         )
@@ -24,9 +28,17 @@ impl Plugin for InternalAudioPlugin {
                 .with_system(nope_audio.system())
                 .with_system(won_audio.system())
                 .with_system(level_up_audio.system())
-                .with_system(dying_audio.system()),
+                .with_system(dying_audio.system())
+                .with_system(enemy_chase_audio.system())
+                .with_system(enemy_repelled_audio.system()),
         )
-        .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(stop_audio.system()));
+        .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(stop_audio.system()))
+        .add_system_set(
+            SystemSet::on_enter(GameState::Paused).with_system(pause_effects_audio.system()),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Paused).with_system(resume_effects_audio.system()),
+        );
     }
 }
 
@@ -35,21 +47,34 @@ struct AudioChannels {
     effects: AudioChannel,
 }
 
-fn start_audio(audio_assets: Res<AudioAssets>, audio: Res<Audio>, channels: Res<AudioChannels>) {
-    audio.set_volume_in_channel(0.2, &channels.background);
+fn start_audio(
+    audio_assets: Res<AudioAssets>,
+    audio: Res<Audio>,
+    channels: Res<AudioChannels>,
+    settings: Res<Settings>,
+) {
+    audio.set_volume_in_channel(settings.background_volume, &channels.background);
     audio.play_looped_in_channel(audio_assets.background.clone(), &channels.background);
 
-    audio.set_volume_in_channel(0.3, &channels.effects);
+    audio.set_volume_in_channel(settings.effects_volume, &channels.effects);
+}
+
+fn apply_volumes(audio: Res<Audio>, channels: Res<AudioChannels>, settings: Res<Settings>) {
+    if settings.is_changed() {
+        audio.set_volume_in_channel(settings.background_volume, &channels.background);
+        audio.set_volume_in_channel(settings.effects_volume, &channels.effects);
+    }
 }
 
 fn befriend_audio(
     audio_assets: Res<AudioAssets>,
     audio: Res<Audio>,
     channels: Res<AudioChannels>,
+    mut rng: ResMut<AudioRng>,
     mut events: EventReader<BefriendEvent>,
 ) {
     if let Some(_event) = events.iter().last() {
-        let random_value = random::<f32>();
+        let random_value = rng.gen::<f32>();
         if random_value > 0.66 {
             audio.play_in_channel(audio_assets.hi_1.clone(), &channels.effects);
         } else if random_value > 0.33 {
@@ -64,10 +89,11 @@ fn nope_audio(
     audio_assets: Res<AudioAssets>,
     audio: Res<Audio>,
     channels: Res<AudioChannels>,
+    mut rng: ResMut<AudioRng>,
     mut events: EventReader<NopeEvent>,
 ) {
     if let Some(_event) = events.iter().last() {
-        let random_value = random::<f32>();
+        let random_value = rng.gen::<f32>();
         if random_value > 0.5 {
             audio.play_in_channel(audio_assets.nope_1.clone(), &channels.effects);
         } else {
@@ -109,6 +135,28 @@ fn level_up_audio(
     }
 }
 
+fn enemy_chase_audio(
+    audio_assets: Res<AudioAssets>,
+    audio: Res<Audio>,
+    channels: Res<AudioChannels>,
+    mut events: EventReader<EnemyChaseEvent>,
+) {
+    if let Some(_event) = events.iter().last() {
+        audio.play_in_channel(audio_assets.enemy_chase.clone(), &channels.effects);
+    }
+}
+
+fn enemy_repelled_audio(
+    audio_assets: Res<AudioAssets>,
+    audio: Res<Audio>,
+    channels: Res<AudioChannels>,
+    mut events: EventReader<EnemyRepelledEvent>,
+) {
+    if let Some(_event) = events.iter().last() {
+        audio.play_in_channel(audio_assets.enemy_repelled.clone(), &channels.effects);
+    }
+}
+
 fn lets_go_audio(audio_assets: Res<AudioAssets>, audio: Res<Audio>, channels: Res<AudioChannels>) {
     audio.play_in_channel(audio_assets.lets_go.clone(), &channels.effects);
 }
@@ -116,3 +164,11 @@ fn lets_go_audio(audio_assets: Res<AudioAssets>, audio: Res<Audio>, channels: Re
 fn stop_audio(audio: Res<Audio>, channels: Res<AudioChannels>) {
     audio.stop_channel(&channels.effects);
 }
+
+fn pause_effects_audio(audio: Res<Audio>, channels: Res<AudioChannels>) {
+    audio.pause_channel(&channels.effects);
+}
+
+fn resume_effects_audio(audio: Res<Audio>, channels: Res<AudioChannels>) {
+    audio.resume_channel(&channels.effects);
+}
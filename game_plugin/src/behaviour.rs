@@ -0,0 +1,212 @@
+use crate::clock::GameClock;
+use crate::entities::{
+    BefriendedEntity, EntitiesSystemLabels, EntityForm, FormationSlot, GameEntity,
+};
+use crate::levels::Levels;
+use crate::player::{Player, PlayerState};
+use crate::rng::GameRng;
+use crate::spatial::SpatialIndex;
+use crate::tuning::Tuning;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::Rng;
+use std::mem::discriminant;
+
+pub struct BehaviourPlugin;
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub enum BehaviourSystemLabels {
+    AssignBehaviours,
+    Steer,
+}
+
+impl Plugin for BehaviourPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(
+                    assign_behaviours
+                        .system()
+                        .label(BehaviourSystemLabels::AssignBehaviours),
+                )
+                .with_system(
+                    steer_entities
+                        .system()
+                        .label(BehaviourSystemLabels::Steer)
+                        .after(BehaviourSystemLabels::AssignBehaviours)
+                        .after(EntitiesSystemLabels::ArrangeFormation)
+                        .before(EntitiesSystemLabels::MoveEntities),
+                ),
+        );
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Behaviour {
+    Wander,
+    FleeFromPlayer,
+    ApproachPlayer,
+    Flock,
+    Patrol { center: Vec2 },
+    Formation,
+}
+
+impl Behaviour {
+    fn for_entity(
+        game_entity: &GameEntity,
+        befriended: bool,
+        known: bool,
+        position: Vec2,
+    ) -> Behaviour {
+        if befriended {
+            return Behaviour::Formation;
+        }
+        if game_entity.true_form == EntityForm::Enemy {
+            return Behaviour::ApproachPlayer;
+        }
+        if !known {
+            return Behaviour::FleeFromPlayer;
+        }
+        match game_entity.true_form {
+            EntityForm::Pentagon | EntityForm::Heptagon => Behaviour::Flock,
+            EntityForm::Hexagon | EntityForm::Octagon => Behaviour::Patrol { center: position },
+            _ => Behaviour::Wander,
+        }
+    }
+
+    fn same_kind(&self, other: &Behaviour) -> bool {
+        discriminant(self) == discriminant(other)
+    }
+}
+
+fn assign_behaviours(
+    mut commands: Commands,
+    player_state: Res<PlayerState>,
+    levels: Res<Levels>,
+    entities: Query<(
+        Entity,
+        &Transform,
+        &GameEntity,
+        Option<&BefriendedEntity>,
+        Option<&Behaviour>,
+    )>,
+) {
+    for (entity, transform, game_entity, befriended, behaviour) in entities.iter() {
+        let new_behaviour = Behaviour::for_entity(
+            game_entity,
+            befriended.is_some(),
+            levels.is_known(&game_entity.true_form, player_state.level),
+            transform.translation.truncate(),
+        );
+        if behaviour.map_or(true, |behaviour| !behaviour.same_kind(&new_behaviour)) {
+            commands.entity(entity).insert(new_behaviour);
+        }
+    }
+}
+
+fn steer_entities(
+    clock: Res<GameClock>,
+    tuning: Res<Tuning>,
+    spatial_index: Res<SpatialIndex>,
+    mut rng: ResMut<GameRng>,
+    player_query: Query<&Transform, With<Player>>,
+    mut entities: Query<(
+        Entity,
+        &Transform,
+        &mut GameEntity,
+        &Behaviour,
+        Option<&FormationSlot>,
+    )>,
+) {
+    let player_position = player_query
+        .single()
+        .map(|transform| transform.translation.truncate())
+        .ok();
+    let steering = &tuning.steering;
+    let flocks: HashMap<Entity, (EntityForm, Vec2)> = entities
+        .iter_mut()
+        .filter(|(_, _, _, behaviour, _)| **behaviour == Behaviour::Flock)
+        .map(|(entity, _, game_entity, _, _)| {
+            (
+                entity,
+                (game_entity.true_form.clone(), game_entity.current_direction),
+            )
+        })
+        .collect();
+    let now = clock.elapsed();
+    for (entity, transform, mut game_entity, behaviour, formation_slot) in entities.iter_mut() {
+        let position = transform.translation.truncate();
+        if let (Behaviour::Formation, Some(slot)) = (behaviour, formation_slot) {
+            // slow down when arriving at the slot and catch up when falling behind
+            let to_slot = (slot.position - position) / tuning.formation.spacing;
+            game_entity.current_direction = if to_slot.length() > tuning.formation.max_speed_factor
+            {
+                to_slot.normalize() * tuning.formation.max_speed_factor
+            } else {
+                to_slot
+            };
+            continue;
+        }
+        let to_player = player_position.map(|player_position| player_position - position);
+        let desired = match (behaviour, to_player) {
+            (Behaviour::FleeFromPlayer, Some(to_player))
+                if to_player.length() < steering.flee_distance =>
+            {
+                Some(-to_player)
+            }
+            (Behaviour::ApproachPlayer, Some(to_player)) => {
+                if to_player.length() < steering.hunt_distance {
+                    Some(to_player)
+                } else {
+                    None
+                }
+            }
+            (Behaviour::Flock, _) => {
+                let heading: Vec2 = spatial_index
+                    .query_circle(position, steering.flock_distance)
+                    .filter(|other| *other != entity)
+                    .filter_map(|other| flocks.get(&other))
+                    .filter(|(form, _)| form == &game_entity.true_form)
+                    .map(|(_, direction)| *direction)
+                    .fold(Vec2::ZERO, |sum, direction| sum + direction);
+                if heading.length() > f32::EPSILON {
+                    Some(heading + game_entity.current_direction)
+                } else {
+                    None
+                }
+            }
+            (Behaviour::Patrol { center }, _) => {
+                let from_center = position - *center;
+                let tangent = Vec2::new(-from_center.y, from_center.x);
+                let correction = normalize_or_zero(from_center)
+                    * (steering.patrol_radius - from_center.length())
+                    / steering.patrol_radius;
+                Some(normalize_or_zero(tangent) + correction)
+            }
+            _ => None,
+        };
+        match desired {
+            Some(desired) if desired.length() > f32::EPSILON => {
+                game_entity.current_direction = desired.normalize();
+            }
+            _ => {
+                if now >= game_entity.next_direction_change {
+                    game_entity.current_direction =
+                        Vec2::new((2. * rng.gen::<f32>()) - 1., (2. * rng.gen::<f32>()) - 1.)
+                            .normalize();
+                    game_entity.next_direction_change =
+                        now + tuning.direction_change_delay(&mut *rng);
+                }
+            }
+        }
+    }
+}
+
+fn normalize_or_zero(vector: Vec2) -> Vec2 {
+    if vector.length() > f32::EPSILON {
+        vector.normalize()
+    } else {
+        Vec2::ZERO
+    }
+}
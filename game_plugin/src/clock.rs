@@ -0,0 +1,91 @@
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+pub struct ClockPlugin;
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub enum ClockSystemLabels {
+    Tick,
+}
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GameClock>().add_system_to_stage(
+            CoreStage::PreUpdate,
+            tick_game_clock.system().label(ClockSystemLabels::Tick),
+        );
+        #[cfg(debug_assertions)]
+        app.add_system(scale_time_with_keys.system());
+    }
+}
+
+// Game time that only advances while playing, so pauses and menu visits do not count
+pub struct GameClock {
+    elapsed: Duration,
+    delta: Duration,
+    scale: f32,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        GameClock {
+            elapsed: Duration::from_secs(0),
+            delta: Duration::from_secs(0),
+            scale: 1.,
+        }
+    }
+}
+
+impl GameClock {
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    // Replaces the delta of the current frame, e.g. with a recorded one
+    pub fn set_delta(&mut self, delta: Duration) {
+        self.elapsed = self.elapsed - self.delta + delta;
+        self.delta = delta;
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.);
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        self.delta = delta.mul_f32(self.scale);
+        self.elapsed += self.delta;
+    }
+}
+
+fn tick_game_clock(time: Res<Time>, state: Res<State<GameState>>, mut clock: ResMut<GameClock>) {
+    if state.current() == &GameState::Playing {
+        clock.tick(time.delta());
+    } else {
+        clock.tick(Duration::from_secs(0));
+    }
+}
+
+// Slow motion and fast forward for debugging
+#[cfg(debug_assertions)]
+fn scale_time_with_keys(keyboard_input: Res<Input<KeyCode>>, mut clock: ResMut<GameClock>) {
+    let scale = if keyboard_input.just_pressed(KeyCode::F1) {
+        0.25
+    } else if keyboard_input.just_pressed(KeyCode::F2) {
+        1.
+    } else if keyboard_input.just_pressed(KeyCode::F3) {
+        4.
+    } else {
+        return;
+    };
+    info!("Game time runs at {}x", scale);
+    clock.set_scale(scale);
+}
@@ -0,0 +1,261 @@
+use crate::clock::GameClock;
+use crate::entities::{BefriendedEntity, EntitiesSystemLabels, EntityKind, GameEntity};
+use crate::player::Player;
+use crate::spatial::{SpatialIndex, SpatialSystemLabels};
+use crate::tuning::Tuning;
+use crate::{GameState, GameWorld};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+pub const ENTITY_RADIUS: f32 = 28.;
+
+pub struct CollisionPlugin;
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub enum CollisionSystemLabels {
+    Collide,
+}
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<BumpEvent>().add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(
+                    collide_entities
+                        .system()
+                        .label(CollisionSystemLabels::Collide)
+                        .after(EntitiesSystemLabels::MoveEntities)
+                        .after(SpatialSystemLabels::UpdateIndex),
+                )
+                .with_system(
+                    collide_player
+                        .system()
+                        .label(CollisionSystemLabels::Collide)
+                        .after(EntitiesSystemLabels::MoveEntities)
+                        .after(SpatialSystemLabels::UpdateIndex),
+                ),
+        );
+    }
+}
+
+pub struct BumpEvent {
+    pub entity: Entity,
+    pub kind: EntityKind,
+}
+
+pub enum Collider {
+    Circle(f32),
+    // vertices in local space, in order
+    Polygon(Vec<Vec2>),
+}
+
+impl Collider {
+    pub fn player() -> Self {
+        let radius = 30.;
+        Collider::Polygon(
+            (0..3)
+                .map(|corner| {
+                    let angle = std::f32::consts::FRAC_PI_2
+                        + corner as f32 * 2. * std::f32::consts::PI / 3.;
+                    Vec2::new(angle.cos(), angle.sin()) * radius
+                })
+                .collect(),
+        )
+    }
+
+    pub fn bounding_radius(&self) -> f32 {
+        match self {
+            Collider::Circle(radius) => *radius,
+            Collider::Polygon(vertices) => vertices
+                .iter()
+                .map(|vertex| vertex.length())
+                .fold(0., f32::max),
+        }
+    }
+
+    // Returns the vector that moves a circle at `center` out of this collider if they overlap
+    pub fn push_out_circle(
+        &self,
+        transform: &Transform,
+        center: Vec2,
+        radius: f32,
+    ) -> Option<Vec2> {
+        match self {
+            Collider::Circle(own_radius) => push_out_of_circle(
+                transform.translation.truncate(),
+                *own_radius,
+                center,
+                radius,
+            ),
+            Collider::Polygon(vertices) => {
+                let world_vertices: Vec<Vec2> = vertices
+                    .iter()
+                    .map(|vertex| transform.mul_vec3(vertex.extend(0.)).truncate())
+                    .collect();
+                let closest = closest_point_on_outline(&world_vertices, center);
+                let difference = center - closest;
+                let distance = difference.length();
+                if contains_point(&world_vertices, center) {
+                    let normal = if distance > f32::EPSILON {
+                        -difference / distance
+                    } else {
+                        Vec2::new(0., 1.)
+                    };
+                    Some(normal * (distance + radius))
+                } else if distance < radius && distance > f32::EPSILON {
+                    Some(difference / distance * (radius - distance))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+fn push_out_of_circle(
+    circle_center: Vec2,
+    circle_radius: f32,
+    center: Vec2,
+    radius: f32,
+) -> Option<Vec2> {
+    let difference = center - circle_center;
+    let distance = difference.length();
+    let penetration = circle_radius + radius - distance;
+    if penetration <= 0. {
+        return None;
+    }
+    let normal = if distance > f32::EPSILON {
+        difference / distance
+    } else {
+        Vec2::new(0., 1.)
+    };
+    Some(normal * penetration)
+}
+
+fn closest_point_on_outline(vertices: &[Vec2], point: Vec2) -> Vec2 {
+    let mut closest = vertices[0];
+    let mut closest_distance = f32::MAX;
+    for (index, start) in vertices.iter().enumerate() {
+        let end = vertices[(index + 1) % vertices.len()];
+        let edge = end - *start;
+        let along = ((point - *start).dot(edge) / edge.length_squared()).clamp(0., 1.);
+        let candidate = *start + edge * along;
+        let distance = candidate.distance_squared(point);
+        if distance < closest_distance {
+            closest = candidate;
+            closest_distance = distance;
+        }
+    }
+    closest
+}
+
+fn contains_point(vertices: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (index, start) in vertices.iter().enumerate() {
+        let end = vertices[(index + 1) % vertices.len()];
+        if (start.y > point.y) != (end.y > point.y)
+            && point.x < (end.x - start.x) * (point.y - start.y) / (end.y - start.y) + start.x
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn collide_player(
+    clock: Res<GameClock>,
+    tuning: Res<Tuning>,
+    world: Res<GameWorld>,
+    spatial_index: Res<SpatialIndex>,
+    mut bump_events: EventWriter<BumpEvent>,
+    player_query: Query<(&Transform, &Collider), With<Player>>,
+    mut entities: Query<
+        (
+            &mut Transform,
+            &mut GameEntity,
+            &Collider,
+            Option<&BefriendedEntity>,
+        ),
+        Without<Player>,
+    >,
+) {
+    let (player_transform, player_collider) = if let Ok(player) = player_query.single() {
+        player
+    } else {
+        return;
+    };
+    let player_position = player_transform.translation.truncate();
+    let reach = player_collider.bounding_radius() + ENTITY_RADIUS;
+    for candidate in spatial_index.query_circle(player_position, reach) {
+        let (mut transform, mut game_entity, collider, befriended) =
+            match entities.get_mut(candidate) {
+                Ok(entity) => entity,
+                Err(_) => continue,
+            };
+        let push = player_collider.push_out_circle(
+            player_transform,
+            transform.translation.truncate(),
+            collider.bounding_radius(),
+        );
+        if let Some(push) = push {
+            transform.translation += push.extend(0.);
+            transform.translation.x = transform.translation.x.clamp(-world.border, world.border);
+            transform.translation.y = transform.translation.y.clamp(-world.border, world.border);
+            if push.length() > f32::EPSILON {
+                game_entity.current_direction = push.normalize();
+            }
+            if game_entity.bump(
+                clock.elapsed(),
+                tuning.bump_cooldown(),
+                befriended.is_some(),
+            ) {
+                bump_events.send(BumpEvent {
+                    entity: candidate,
+                    kind: game_entity.kind(befriended.is_some()),
+                });
+            }
+        }
+    }
+}
+
+fn collide_entities(
+    world: Res<GameWorld>,
+    spatial_index: Res<SpatialIndex>,
+    mut entities: Query<(Entity, &mut Transform, &mut GameEntity, &Collider)>,
+) {
+    let bodies: HashMap<Entity, (Vec2, f32)> = entities
+        .iter_mut()
+        .map(|(entity, transform, _, collider)| {
+            (
+                entity,
+                (transform.translation.truncate(), collider.bounding_radius()),
+            )
+        })
+        .collect();
+    let mut pushes: Vec<(Entity, Vec2)> = vec![];
+    for (entity, (position, radius)) in bodies.iter() {
+        for other in spatial_index.query_circle(*position, radius + ENTITY_RADIUS) {
+            if other <= *entity {
+                continue;
+            }
+            if let Some((other_position, other_radius)) = bodies.get(&other) {
+                if let Some(push) =
+                    push_out_of_circle(*position, *radius, *other_position, *other_radius)
+                {
+                    pushes.push((other, push / 2.));
+                    pushes.push((*entity, -push / 2.));
+                }
+            }
+        }
+    }
+    for (entity, push) in pushes {
+        if let Ok((_, mut transform, mut game_entity, _)) = entities.get_mut(entity) {
+            transform.translation += push.extend(0.);
+            transform.translation.x = transform.translation.x.clamp(-world.border, world.border);
+            transform.translation.y = transform.translation.y.clamp(-world.border, world.border);
+            if push.length() > f32::EPSILON {
+                game_entity.current_direction = push.normalize();
+            }
+        }
+    }
+}
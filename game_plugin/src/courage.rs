@@ -0,0 +1,456 @@
+use crate::levels::Levels;
+use crate::player::PlayerState;
+use crate::tuning::Tuning;
+
+// The courage rules of a run, kept free of any ECS types so they can be tested on their own
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Encounter {
+    // An entity of a known form was befriended
+    Befriend { form_level: usize },
+    // The player looked at an entity they are not ready for
+    Nope,
+    Bump { courage: f32 },
+    // Courage continuously taken by enemies nearby
+    Drain { courage: f32 },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CourageEvent {
+    Befriend,
+    Nope,
+    LevelUp,
+    Won,
+    Dying,
+}
+
+const LEVEL_UP_COURAGE: f32 = 99.5;
+// Befriending close to a level up does not get its own sound
+const QUIET_BEFRIEND_COURAGE: f32 = 99.6;
+const DYING_COURAGE: f32 = 0.1;
+
+impl PlayerState {
+    pub fn is_over(&self) -> bool {
+        self.dead || self.won
+    }
+
+    pub fn encounter(
+        &mut self,
+        encounter: Encounter,
+        levels: &Levels,
+        tuning: &Tuning,
+    ) -> Option<CourageEvent> {
+        if self.is_over() {
+            return None;
+        }
+        let loss_factor = tuning.formation.courage_loss_factor(self.nearby_friends);
+        let difficulty = tuning.difficulty(self.difficulty);
+        match encounter {
+            Encounter::Befriend { form_level } => {
+                let level_diff = self.level.saturating_sub(form_level).max(1);
+                self.courage += levels.level(self.level).befriend_courage * difficulty.courage_gain
+                    / level_diff as f32;
+                if self.courage < QUIET_BEFRIEND_COURAGE {
+                    return Some(CourageEvent::Befriend);
+                }
+            }
+            Encounter::Nope => {
+                self.courage -=
+                    levels.level(self.level).nope_courage * loss_factor * difficulty.courage_loss;
+                if self.courage > DYING_COURAGE {
+                    return Some(CourageEvent::Nope);
+                }
+            }
+            Encounter::Bump { courage } => {
+                let courage = if courage < 0. {
+                    courage * loss_factor * difficulty.courage_loss
+                } else {
+                    courage
+                };
+                self.courage = (self.courage + courage).clamp(0., 100.);
+                if courage < 0. && self.courage > DYING_COURAGE {
+                    return Some(CourageEvent::Nope);
+                }
+            }
+            Encounter::Drain { courage } => {
+                self.courage = (self.courage - courage * loss_factor * difficulty.courage_loss)
+                    .clamp(0., 100.);
+            }
+        }
+        None
+    }
+
+    // Applied once per frame after all encounters
+    pub fn settle(&mut self, levels: &Levels, tuning: &Tuning) -> Option<CourageEvent> {
+        if self.is_over() {
+            return None;
+        }
+        self.courage = self.courage.clamp(0., 100.);
+        if self.courage > LEVEL_UP_COURAGE {
+            self.level += 1;
+            if levels.has_won(self.level) {
+                self.courage = 100.;
+                self.won = true;
+                return Some(CourageEvent::Won);
+            }
+            self.courage = levels.level(self.level).starting_courage
+                * tuning.difficulty(self.difficulty).starting_courage;
+            return Some(CourageEvent::LevelUp);
+        }
+        if self.courage < DYING_COURAGE {
+            self.dead = true;
+            return Some(CourageEvent::Dying);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+
+    const LEVELS: &str = r#"(
+        initial_entities: 0,
+        win_level: 3,
+        levels: [
+            (form: Rectangle, initial_count: 0, starting_courage: 50.0, befriend_courage: 20.0,
+                nope_courage: 20.0, spawn_interval: 2.2, enemy_fraction: 0.8),
+            (form: Pentagon, initial_count: 0, starting_courage: 25.0, befriend_courage: 20.0,
+                nope_courage: 10.0, spawn_interval: 2.2, enemy_fraction: 0.8),
+            (form: Hexagon, initial_count: 0, starting_courage: 30.0, befriend_courage: 40.0,
+                nope_courage: 20.0, spawn_interval: 2.2, enemy_fraction: 0.8),
+        ],
+    )"#;
+
+    fn levels() -> Levels {
+        ron::from_str(LEVELS).unwrap()
+    }
+
+    fn player(level: usize, courage: f32) -> PlayerState {
+        let mut player_state = PlayerState::new(courage);
+        player_state.level = level;
+        player_state
+    }
+
+    fn befriend(form_level: usize) -> Encounter {
+        Encounter::Befriend { form_level }
+    }
+
+    #[test]
+    fn befriending_the_current_form_gives_full_courage() {
+        let mut player_state = player(1, 25.);
+        let event = player_state.encounter(befriend(1), &levels(), &Tuning::default());
+        assert_eq!(event, Some(CourageEvent::Befriend));
+        assert_eq!(player_state.courage, 45.);
+    }
+
+    #[test]
+    fn befriending_one_level_below_gives_full_courage() {
+        let mut player_state = player(1, 25.);
+        player_state.encounter(befriend(0), &levels(), &Tuning::default());
+        assert_eq!(player_state.courage, 45.);
+    }
+
+    #[test]
+    fn befriending_older_forms_is_worth_less() {
+        let mut player_state = player(2, 30.);
+        player_state.encounter(befriend(0), &levels(), &Tuning::default());
+        assert_eq!(player_state.courage, 50.);
+    }
+
+    #[test]
+    fn befriending_right_before_a_level_up_is_quiet() {
+        let mut player_state = player(0, 85.);
+        let event = player_state.encounter(befriend(0), &levels(), &Tuning::default());
+        assert_eq!(event, None);
+        assert_eq!(player_state.courage, 105.);
+    }
+
+    #[test]
+    fn nope_costs_the_courage_of_the_current_level() {
+        let mut player_state = player(1, 25.);
+        let event = player_state.encounter(Encounter::Nope, &levels(), &Tuning::default());
+        assert_eq!(event, Some(CourageEvent::Nope));
+        assert_eq!(player_state.courage, 15.);
+    }
+
+    #[test]
+    fn nope_is_quiet_when_it_empties_the_courage() {
+        let mut player_state = player(0, 15.);
+        let event = player_state.encounter(Encounter::Nope, &levels(), &Tuning::default());
+        assert_eq!(event, None);
+        assert_eq!(
+            player_state.settle(&levels(), &Tuning::default()),
+            Some(CourageEvent::Dying)
+        );
+        assert!(player_state.dead);
+        assert_eq!(player_state.courage, 0.);
+    }
+
+    #[test]
+    fn nearby_friends_soften_courage_loss() {
+        let tuning = Tuning::default();
+        let mut alone = player(0, 50.);
+        let mut with_friends = player(0, 50.);
+        with_friends.nearby_friends = 2;
+        alone.encounter(Encounter::Nope, &levels(), &tuning);
+        with_friends.encounter(Encounter::Nope, &levels(), &tuning);
+        assert!(with_friends.courage > alone.courage);
+        let factor = tuning.formation.courage_loss_factor(2);
+        assert!((with_friends.courage - (50. - 20. * factor)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn courage_loss_never_drops_below_the_minimum_factor() {
+        let tuning = Tuning::default();
+        let mut player_state = player(0, 50.);
+        player_state.nearby_friends = 1000;
+        player_state.encounter(Encounter::Nope, &levels(), &tuning);
+        let expected = 50. - 20. * tuning.formation.min_courage_loss;
+        assert!((player_state.courage - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn friends_do_not_change_courage_gains() {
+        let mut player_state = player(0, 50.);
+        player_state.nearby_friends = 3;
+        player_state.encounter(befriend(0), &levels(), &Tuning::default());
+        assert_eq!(player_state.courage, 70.);
+    }
+
+    #[test]
+    fn difficulty_scales_befriending_and_nope() {
+        let tuning = Tuning::default();
+        let mut easy = player(1, 50.);
+        easy.difficulty = Difficulty::Easy;
+        let mut hard = player(1, 50.);
+        hard.difficulty = Difficulty::Hard;
+        easy.encounter(befriend(1), &levels(), &tuning);
+        hard.encounter(befriend(1), &levels(), &tuning);
+        assert!(easy.courage > 70.);
+        assert!(hard.courage < 70.);
+        easy.courage = 50.;
+        hard.courage = 50.;
+        easy.encounter(Encounter::Nope, &levels(), &tuning);
+        hard.encounter(Encounter::Nope, &levels(), &tuning);
+        assert!(easy.courage > 40.);
+        assert!(hard.courage < 40.);
+        easy.courage = 50.;
+        hard.courage = 50.;
+        let bump = Encounter::Bump { courage: -10. };
+        easy.encounter(bump, &levels(), &tuning);
+        hard.encounter(bump, &levels(), &tuning);
+        assert!(easy.courage > 40.);
+        assert!(hard.courage < 40.);
+        easy.courage = 50.;
+        hard.courage = 50.;
+        let drain = Encounter::Drain { courage: 10. };
+        easy.encounter(drain, &levels(), &tuning);
+        hard.encounter(drain, &levels(), &tuning);
+        assert!(easy.courage > 40.);
+        assert!(hard.courage < 40.);
+    }
+
+    #[test]
+    fn difficulty_scales_courage_after_a_level_up() {
+        let tuning = Tuning::default();
+        let mut easy = player(0, 100.);
+        easy.difficulty = Difficulty::Easy;
+        let mut hard = player(0, 100.);
+        hard.difficulty = Difficulty::Hard;
+        assert_eq!(easy.settle(&levels(), &tuning), Some(CourageEvent::LevelUp));
+        assert_eq!(hard.settle(&levels(), &tuning), Some(CourageEvent::LevelUp));
+        assert!(easy.courage > 25.);
+        assert!(hard.courage < 25.);
+    }
+
+    #[test]
+    fn bumps_are_clamped_immediately() {
+        let mut player_state = player(0, 99.);
+        let event = player_state.encounter(
+            Encounter::Bump { courage: 5. },
+            &levels(),
+            &Tuning::default(),
+        );
+        assert_eq!(event, None);
+        assert_eq!(player_state.courage, 100.);
+    }
+
+    #[test]
+    fn painful_bumps_say_nope() {
+        let mut player_state = player(0, 50.);
+        let event = player_state.encounter(
+            Encounter::Bump { courage: -10. },
+            &levels(),
+            &Tuning::default(),
+        );
+        assert_eq!(event, Some(CourageEvent::Nope));
+        assert_eq!(player_state.courage, 40.);
+    }
+
+    #[test]
+    fn drain_is_silent_and_clamped() {
+        let mut player_state = player(0, 1.);
+        let event = player_state.encounter(
+            Encounter::Drain { courage: 5. },
+            &levels(),
+            &Tuning::default(),
+        );
+        assert_eq!(event, None);
+        assert_eq!(player_state.courage, 0.);
+    }
+
+    #[test]
+    fn settle_clamps_courage() {
+        let mut player_state = player(0, -5.);
+        player_state.settle(&levels(), &Tuning::default());
+        assert_eq!(player_state.courage, 0.);
+        let mut player_state = player(0, 70.);
+        player_state.courage = 120.;
+        player_state.level = 2;
+        player_state.settle(&levels(), &Tuning::default());
+        assert_eq!(player_state.courage, 100.);
+    }
+
+    #[test]
+    fn full_courage_levels_up_and_resets_courage() {
+        let mut player_state = player(0, 99.6);
+        assert_eq!(
+            player_state.settle(&levels(), &Tuning::default()),
+            Some(CourageEvent::LevelUp)
+        );
+        assert_eq!(player_state.level, 1);
+        assert_eq!(player_state.courage, 25.);
+    }
+
+    #[test]
+    fn almost_full_courage_does_not_level_up() {
+        let mut player_state = player(0, 99.5);
+        assert_eq!(player_state.settle(&levels(), &Tuning::default()), None);
+        assert_eq!(player_state.level, 0);
+    }
+
+    #[test]
+    fn leveling_up_past_the_win_level_wins() {
+        let mut player_state = player(2, 100.);
+        assert_eq!(
+            player_state.settle(&levels(), &Tuning::default()),
+            Some(CourageEvent::Won)
+        );
+        assert!(player_state.won);
+        assert_eq!(player_state.level, 3);
+        assert_eq!(player_state.courage, 100.);
+    }
+
+    #[test]
+    fn courage_just_above_zero_survives() {
+        let mut player_state = player(0, 0.1);
+        assert_eq!(player_state.settle(&levels(), &Tuning::default()), None);
+        assert!(!player_state.dead);
+    }
+
+    #[test]
+    fn finished_runs_ignore_encounters() {
+        let levels = levels();
+        let tuning = Tuning::default();
+        let mut dead = player(0, 0.);
+        dead.dead = true;
+        assert_eq!(dead.encounter(Encounter::Nope, &levels, &tuning), None);
+        assert_eq!(dead.settle(&levels, &tuning), None);
+        assert_eq!(dead.courage, 0.);
+        let mut won = player(3, 100.);
+        won.won = true;
+        assert_eq!(won.encounter(befriend(2), &levels, &tuning), None);
+        assert_eq!(won.settle(&levels, &tuning), None);
+        assert_eq!(won.level, 3);
+    }
+
+    #[test]
+    fn encounters_within_a_frame_add_up_before_settling() {
+        let levels = levels();
+        let tuning = Tuning::default();
+        let mut player_state = player(0, 90.);
+        player_state.encounter(befriend(0), &levels, &tuning);
+        player_state.encounter(Encounter::Nope, &levels, &tuning);
+        assert_eq!(player_state.settle(&levels, &tuning), None);
+        assert_eq!(player_state.courage, 90.);
+    }
+
+    #[test]
+    fn bundled_campaign_starts_with_courage_to_spare() {
+        let levels = Levels::default();
+        let player_state = PlayerState::new(levels.level(0).starting_courage);
+        assert!(player_state.courage > levels.level(0).nope_courage);
+    }
+
+    #[test]
+    fn bundled_campaign_can_be_won_by_befriending_the_current_form() {
+        let levels = Levels::default();
+        let tuning = Tuning::default();
+        let mut player_state = PlayerState::new(levels.level(0).starting_courage);
+        let mut encounters = 0;
+        while !player_state.won {
+            let level = player_state.level;
+            player_state.encounter(befriend(level), &levels, &tuning);
+            player_state.settle(&levels, &tuning);
+            encounters += 1;
+            assert!(encounters < 1000, "The campaign cannot be won");
+        }
+        assert_eq!(player_state.level, levels.win_level());
+    }
+
+    #[test]
+    fn bundled_campaign_survives_a_nope_after_each_level_up() {
+        let levels = Levels::default();
+        let tuning = Tuning::default();
+        for level in 1..levels.win_level() {
+            let mut player_state = player(level - 1, 100.);
+            assert_eq!(
+                player_state.settle(&levels, &tuning),
+                Some(CourageEvent::LevelUp)
+            );
+            player_state.encounter(Encounter::Nope, &levels, &tuning);
+            assert_eq!(
+                player_state.settle(&levels, &tuning),
+                None,
+                "level {}",
+                level
+            );
+        }
+    }
+
+    #[test]
+    fn bundled_difficulties_keep_spawning_friends() {
+        let levels = Levels::default();
+        let tuning = Tuning::default();
+        assert!(tuning.difficulty.validate(&levels).is_ok());
+        let hard = tuning.difficulty(Difficulty::Hard);
+        for level in levels.iter() {
+            assert!(hard.enemy_fraction(level) < 1.);
+        }
+    }
+
+    #[test]
+    fn bundled_campaign_can_be_won_on_hard() {
+        let levels = Levels::default();
+        let tuning = Tuning::default();
+        let hard = tuning.difficulty(Difficulty::Hard);
+        let mut player_state =
+            PlayerState::new(levels.level(0).starting_courage * hard.starting_courage);
+        player_state.difficulty = Difficulty::Hard;
+        assert!(player_state.courage > levels.level(0).nope_courage * hard.courage_loss);
+        let mut encounters = 0;
+        while !player_state.won {
+            let level = player_state.level;
+            player_state.encounter(befriend(level), &levels, &tuning);
+            assert_ne!(
+                player_state.settle(&levels, &tuning),
+                Some(CourageEvent::Dying)
+            );
+            encounters += 1;
+            assert!(encounters < 1000, "The campaign cannot be won on hard");
+        }
+        assert_eq!(player_state.level, levels.win_level());
+    }
+}
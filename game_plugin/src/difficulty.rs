@@ -0,0 +1,109 @@
+use crate::levels::{Level, Levels};
+use crate::tuning::Tuning;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::Normal
+    }
+}
+
+impl Difficulty {
+    pub fn next(&self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+}
+
+// Factors applied to the level and tuning values, all 1 for `Normal`
+#[derive(Deserialize, Clone)]
+pub struct DifficultyModifiers {
+    pub spawn_interval: f32,
+    pub enemy_fraction: f32,
+    pub courage_gain: f32,
+    pub courage_loss: f32,
+    pub starting_courage: f32,
+    pub field_of_view: f32,
+}
+
+impl DifficultyModifiers {
+    fn has_positive_factors(&self) -> bool {
+        [
+            self.spawn_interval,
+            self.enemy_fraction,
+            self.courage_gain,
+            self.courage_loss,
+            self.starting_courage,
+            self.field_of_view,
+        ]
+        .iter()
+        .all(|factor| *factor > 0.)
+    }
+
+    // The enemy fraction of every level has to stay a chance, or only enemies would spawn
+    pub fn is_valid(&self, levels: &Levels) -> bool {
+        self.has_positive_factors()
+            && levels
+                .iter()
+                .all(|level| level.enemy_fraction * self.enemy_fraction <= 1.)
+    }
+
+    // Chance of a newly spawned entity being an enemy
+    pub fn enemy_fraction(&self, level: &Level) -> f32 {
+        (level.enemy_fraction * self.enemy_fraction).clamp(0., 1.)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct DifficultyPresets {
+    pub easy: DifficultyModifiers,
+    pub normal: DifficultyModifiers,
+    pub hard: DifficultyModifiers,
+}
+
+impl DifficultyPresets {
+    pub fn all(&self) -> [&DifficultyModifiers; 3] {
+        [&self.easy, &self.normal, &self.hard]
+    }
+
+    pub fn has_positive_factors(&self) -> bool {
+        self.all()
+            .iter()
+            .all(|modifiers| modifiers.has_positive_factors())
+    }
+
+    // Tuning and levels are loaded separately, so this is checked whenever one of them changes
+    pub fn validate(&self, levels: &Levels) -> Result<(), anyhow::Error> {
+        if self
+            .all()
+            .iter()
+            .all(|modifiers| modifiers.is_valid(levels))
+        {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "A difficulty makes the enemy fraction of a level exceed 1"
+            ))
+        }
+    }
+}
+
+impl Tuning {
+    pub fn difficulty(&self, difficulty: Difficulty) -> &DifficultyModifiers {
+        match difficulty {
+            Difficulty::Easy => &self.difficulty.easy,
+            Difficulty::Normal => &self.difficulty.normal,
+            Difficulty::Hard => &self.difficulty.hard,
+        }
+    }
+}
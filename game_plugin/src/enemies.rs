@@ -0,0 +1,160 @@
+use crate::behaviour::BehaviourSystemLabels;
+use crate::clock::GameClock;
+use crate::courage::Encounter;
+use crate::entities::{EntitiesSystemLabels, GameEntity};
+use crate::levels::Levels;
+use crate::player::{FieldOfView, Player, PlayerState, PlayerSystemLabels};
+use crate::tuning::Tuning;
+use crate::visibility::{VisibilityPolygon, VisibilitySystemLabels};
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+pub struct EnemiesPlugin;
+
+impl Plugin for EnemiesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<EnemyChaseEvent>()
+            .add_event::<EnemyRepelledEvent>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(
+                        repel_enemies
+                            .system()
+                            .after(VisibilitySystemLabels::Compute)
+                            .before(BehaviourSystemLabels::Steer),
+                    )
+                    .with_system(
+                        steer_enemies
+                            .system()
+                            .after(VisibilitySystemLabels::Compute)
+                            .after(BehaviourSystemLabels::Steer)
+                            .before(EntitiesSystemLabels::MoveEntities),
+                    )
+                    .with_system(
+                        drain_courage
+                            .system()
+                            .before(PlayerSystemLabels::SettleCourage),
+                    ),
+            );
+    }
+}
+
+pub struct EnemyChaseEvent;
+pub struct EnemyRepelledEvent;
+
+#[derive(Default, Clone)]
+pub struct Enemy {
+    pub chasing: bool,
+    pub repelled_until: Duration,
+}
+
+impl Enemy {
+    fn is_repelled(&self, now: Duration) -> bool {
+        now < self.repelled_until
+    }
+}
+
+fn repel_enemies(
+    clock: Res<GameClock>,
+    tuning: Res<Tuning>,
+    player_state: Res<PlayerState>,
+    visibility: Res<VisibilityPolygon>,
+    field_of_view: Query<(&Transform, &FieldOfView)>,
+    mut enemies: Query<(Entity, &Transform, &mut Enemy)>,
+    mut repelled_event: EventWriter<EnemyRepelledEvent>,
+) {
+    if player_state.dead || player_state.won || player_state.courage < tuning.enemies.repel_courage
+    {
+        return;
+    }
+    let (fov_transform, field_of_view) = if let Ok(field_of_view) = field_of_view.single() {
+        field_of_view
+    } else {
+        return;
+    };
+    let now = clock.elapsed();
+    for (entity, transform, mut enemy) in enemies.iter_mut() {
+        if !field_of_view.sees(fov_transform, transform.translation)
+            || !visibility.is_visible(entity, transform.translation.truncate())
+        {
+            continue;
+        }
+        if !enemy.is_repelled(now) {
+            repelled_event.send(EnemyRepelledEvent);
+        }
+        enemy.repelled_until = now + tuning.enemies.repel_duration();
+        enemy.chasing = false;
+    }
+}
+
+// Runs after the generic steering: enemies hunt the player they can see unless they were repelled
+fn steer_enemies(
+    clock: Res<GameClock>,
+    tuning: Res<Tuning>,
+    player_state: Res<PlayerState>,
+    visibility: Res<VisibilityPolygon>,
+    player_query: Query<&Transform, With<Player>>,
+    mut enemies: Query<(Entity, &Transform, &mut GameEntity, &mut Enemy)>,
+    mut chase_event: EventWriter<EnemyChaseEvent>,
+) {
+    let player_position = if let Ok(player_transform) = player_query.single() {
+        player_transform.translation.truncate()
+    } else {
+        return;
+    };
+    let now = clock.elapsed();
+    for (entity, transform, mut game_entity, mut enemy) in enemies.iter_mut() {
+        let from_player = transform.translation.truncate() - player_position;
+        if enemy.is_repelled(now) {
+            if from_player.length() > f32::EPSILON {
+                game_entity.current_direction =
+                    from_player.normalize() * tuning.enemies.repel_speed_factor;
+            }
+            continue;
+        }
+        let sees_player = !player_state.dead
+            && !player_state.won
+            && from_player.length() < tuning.steering.hunt_distance
+            // sight lines are symmetric, so the player's occlusion test works the other way round
+            && visibility.is_visible(entity, transform.translation.truncate());
+        if sees_player && !enemy.chasing {
+            chase_event.send(EnemyChaseEvent);
+        }
+        enemy.chasing = sees_player;
+    }
+}
+
+fn drain_courage(
+    clock: Res<GameClock>,
+    levels: Res<Levels>,
+    tuning: Res<Tuning>,
+    mut player_state: ResMut<PlayerState>,
+    player_query: Query<&Transform, With<Player>>,
+    enemies: Query<(&Transform, &Enemy)>,
+) {
+    let player_position = if let Ok(player_transform) = player_query.single() {
+        player_transform.translation.truncate()
+    } else {
+        return;
+    };
+    let now = clock.elapsed();
+    let draining = enemies
+        .iter()
+        .filter(|(transform, enemy)| {
+            !enemy.is_repelled(now)
+                && transform.translation.truncate().distance(player_position)
+                    < tuning.enemies.drain_distance
+        })
+        .count();
+    if draining == 0 {
+        return;
+    }
+    player_state.encounter(
+        Encounter::Drain {
+            courage: draining as f32 * tuning.enemies.drain_per_second * clock.delta_seconds(),
+        },
+        &levels,
+        &tuning,
+    );
+}
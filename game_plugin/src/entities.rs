@@ -1,18 +1,35 @@
-use crate::player::{LevelUpEvent, PlayerState};
+use crate::clock::GameClock;
+use crate::collision::{Collider, ENTITY_RADIUS};
+use crate::enemies::Enemy;
+use crate::journal::Journal;
+use crate::levels::Levels;
+use crate::player::{FieldOfView, LevelUpEvent, Player, PlayerState};
+use crate::rng::GameRng;
+use crate::savegame::ContinuedRun;
+use crate::settings::Settings;
+use crate::spawn::{pick_spawn_position, FIELD_OF_VIEW_MARGIN};
+use crate::tuning::Tuning;
 use crate::{GameState, GameWorld};
 use bevy::prelude::*;
-use bevy::utils::Duration;
+use bevy::utils::{Duration, HashSet};
+use bevy_prototype_lyon::entity::{Path, ShapeBundle};
 use bevy_prototype_lyon::prelude::shapes::*;
 use bevy_prototype_lyon::prelude::*;
-use rand::distributions::Standard;
-use rand::prelude::Distribution;
-use rand::{random, thread_rng, Rng};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
 
 pub struct EntitiesPlugin;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
 struct SpawnEntityStage;
 
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub enum EntitiesSystemLabels {
+    MoveEntities,
+    ArrangeFormation,
+}
+
 impl Plugin for EntitiesPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.insert_resource(EntityTimer::from_seconds(2.2, true))
@@ -22,48 +39,36 @@ impl Plugin for EntitiesPlugin {
             )
             .add_system_set(
                 SystemSet::on_update(GameState::Playing)
-                    .with_system(move_entities.system())
+                    .with_system(
+                        move_entities
+                            .system()
+                            .label(EntitiesSystemLabels::MoveEntities),
+                    )
+                    .with_system(
+                        arrange_formation
+                            .system()
+                            .label(EntitiesSystemLabels::ArrangeFormation),
+                    )
                     .with_system(redraw_after_level_up.system())
-                    .with_system(spawn_entity.system()),
+                    .with_system(draw_acquaintance_rings.system())
+                    .with_system(update_hints.system())
+                    .with_system(spawn_entity.system())
+                    .with_system(despawn_entities.system()),
             )
             .add_system_set(
-                SystemSet::on_exit(GameState::Playing).with_system(remove_entities.system()),
+                SystemSet::on_exit(GameState::Playing)
+                    .with_system(remove_entities.system())
+                    .with_system(remove_hints.system()),
             );
     }
 }
 
-type EntityTimer = Timer;
-
-pub enum Spawn {
-    UpLeft,
-    UpRight,
-    BottomLeft,
-    BottomRight,
-}
-
-impl Spawn {
-    pub fn get_position(&self) -> Vec2 {
-        match self {
-            Spawn::UpLeft => Vec2::new(250., -250.),
-            Spawn::UpRight => Vec2::new(250., 250.),
-            Spawn::BottomLeft => Vec2::new(-250., -250.),
-            Spawn::BottomRight => Vec2::new(-250., 250.),
-        }
-    }
-}
+pub type EntityTimer = Timer;
 
-impl Distribution<Spawn> for Standard {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Spawn {
-        match rng.gen_range(0..4) {
-            0 => Spawn::UpLeft,
-            1 => Spawn::UpRight,
-            2 => Spawn::BottomLeft,
-            _ => Spawn::BottomRight,
-        }
-    }
-}
+// Entities closer to the player than this are never despawned for their age
+const VISIBLE_DISTANCE: f32 = 600.;
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
 pub enum EntityForm {
     Rectangle,
     Pentagon,
@@ -114,130 +119,125 @@ impl EntityForm {
             ..shapes::RegularPolygon::default()
         }
     }
-
-    pub fn level(&self) -> usize {
-        match self {
-            EntityForm::Rectangle => 0,
-            EntityForm::Pentagon => 1,
-            EntityForm::Hexagon => 2,
-            EntityForm::Heptagon => 3,
-            EntityForm::Octagon => 4,
-            EntityForm::Nonagon => 5,
-            EntityForm::Enemy => 99,
-        }
-    }
 }
 
-impl Distribution<EntityForm> for Standard {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> EntityForm {
-        match rng.gen_range(0..6) {
-            0 => EntityForm::Rectangle,
-            1 => EntityForm::Pentagon,
-            2 => EntityForm::Hexagon,
-            3 => EntityForm::Heptagon,
-            4 => EntityForm::Octagon,
-            _ => EntityForm::Nonagon,
-        }
-    }
+pub struct BefriendedEntity;
+
+// Where a befriended entity wants to be in the group trailing the player
+#[derive(Default)]
+pub struct FormationSlot {
+    pub position: Vec2,
 }
 
-pub struct BefriendedEntity;
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    Enemy,
+    Unknown,
+    Befriended,
+}
 
 #[derive(Clone)]
 pub struct GameEntity {
     pub true_form: EntityForm,
     pub current_direction: Vec2,
     pub last_contact: Duration,
+    // Bumps have their own cooldown, so they do not delay a nope or the other way round
+    pub last_bump: Option<Duration>,
     pub next_direction_change: Duration,
+    pub spawned_at: Duration,
     pub known: bool,
+    // Progress of getting to know the entity, befriended at 1
+    pub acquaintance: f32,
 }
 
-fn spawn_beginning_entities(mut commands: Commands) {
-    for count in 0..30 {
-        let form: EntityForm = if count < 5 {
-            EntityForm::Rectangle
-        } else if count < 10 {
-            EntityForm::Pentagon
-        } else {
-            random()
-        };
-        let entity = GameEntity {
-            true_form: form.clone(),
-            current_direction: Vec2::new((2. * random::<f32>()) - 1., (2. * random::<f32>()) - 1.)
-                .normalize(),
-            last_contact: Duration::from_secs(0),
-            next_direction_change: Duration::from_secs(2)
-                + Duration::from_secs(3).mul_f32(random::<f32>()),
-            known: false,
+impl GameEntity {
+    // Friends cheer the player up once, after that bumping into them has no effect.
+    // Everything else bumps again once the cooldown is over.
+    pub fn bump(&mut self, now: Duration, cooldown: Duration, befriended: bool) -> bool {
+        let ready = match self.last_bump {
+            None => true,
+            Some(_) if befriended => false,
+            Some(last_bump) => now.checked_sub(last_bump).unwrap_or_default() > cooldown,
         };
-        let position = Vec2::new(random::<f32>() - 0.5, random::<f32>() - 0.5).normalize() * 500.;
-        if entity.true_form.level() == 0 {
-            commands
-                .spawn_bundle(GeometryBuilder::build_as(
-                    &entity.true_form.to_shape(),
-                    ShapeColors {
-                        main: Color::AQUAMARINE,
-                        outline: Color::ANTIQUE_WHITE,
-                    },
-                    DrawMode::Fill(FillOptions::default()),
-                    Transform::from_translation(Vec3::new(position.x, position.y, 10.)),
-                ))
-                .insert(entity);
+        if ready {
+            self.last_bump = Some(now);
+        }
+        ready
+    }
+
+    pub fn kind(&self, befriended: bool) -> EntityKind {
+        if befriended {
+            EntityKind::Befriended
+        } else if self.true_form == EntityForm::Enemy {
+            EntityKind::Enemy
         } else {
-            commands
-                .spawn_bundle(GeometryBuilder::build_as(
-                    &Circle {
-                        radius: 26.,
-                        center: Default::default(),
-                    },
-                    ShapeColors {
-                        main: Color::DARK_GRAY,
-                        outline: Color::ANTIQUE_WHITE,
-                    },
-                    DrawMode::Fill(FillOptions::default()),
-                    Transform::from_translation(Vec3::new(position.x, position.y, 10.)),
-                ))
-                .insert(entity);
+            EntityKind::Unknown
         }
     }
 }
 
-fn spawn_entity(
+fn spawn_beginning_entities(
     mut commands: Commands,
-    player_state: Res<PlayerState>,
-    mut timer: ResMut<EntityTimer>,
-    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    levels: Res<Levels>,
+    tuning: Res<Tuning>,
+    journal: Res<Journal>,
+    game_world: Res<GameWorld>,
+    clock: Res<GameClock>,
+    settings: Res<Settings>,
+    continued_run: Res<ContinuedRun>,
 ) {
-    if player_state.level > 5 || player_state.dead {
-        return;
-    }
-    if !timer.tick(time.delta()).just_finished() {
+    if continued_run.0.is_some() {
         return;
     }
-    let spawn: Spawn = random();
-    let position = spawn.get_position();
-    let entity = GameEntity {
-        true_form: if random::<f32>() > 0.8 {
-            random()
-        } else {
-            EntityForm::Enemy
-        },
-        current_direction: Vec2::new((2. * random::<f32>()) - 1., (2. * random::<f32>()) - 1.)
+    let spawn_regions = levels.spawn_regions(0);
+    // the player and field of view are spawned at the origin in the same frame
+    let field_of_view =
+        FieldOfView::with_size(tuning.difficulty(settings.difficulty).field_of_view);
+    let keep_away = field_of_view.height() + FIELD_OF_VIEW_MARGIN;
+    for form in levels.initial_forms(&mut *rng) {
+        let entity = GameEntity {
+            true_form: form.clone(),
+            current_direction: Vec2::new(
+                (2. * rng.gen::<f32>()) - 1.,
+                (2. * rng.gen::<f32>()) - 1.,
+            )
             .normalize(),
-        last_contact: time.time_since_startup(),
-        next_direction_change: time.time_since_startup() + Duration::from_secs(2),
-        known: false,
-    };
-    let draw_mode = if entity.true_form == EntityForm::Enemy {
-        DrawMode::Stroke(
-            StrokeOptions::default()
-                .with_line_join(LineJoin::Round)
-                .with_line_width(3.),
-        )
-    } else {
-        DrawMode::Fill(FillOptions::default())
-    };
-    if entity.true_form == EntityForm::Enemy {
+            last_contact: Duration::from_secs(0),
+            last_bump: None,
+            next_direction_change: tuning.direction_change_delay(&mut *rng),
+            spawned_at: clock.elapsed(),
+            known: journal.has_met(&form),
+            acquaintance: 0.,
+        };
+        let position = pick_spawn_position(
+            &mut *rng,
+            &spawn_regions,
+            game_world.border,
+            Vec2::ZERO,
+            keep_away,
+            tuning.despawn_distance,
+        );
+        let form_known = levels.is_known(&entity.true_form, 0);
+        spawn_game_entity(
+            &mut commands,
+            entity,
+            Transform::from_translation(Vec3::new(position.x, position.y, 10.)),
+            form_known,
+            false,
+        );
+    }
+}
+
+// Spawns an entity with the looks matching what the player knows about it
+pub fn spawn_game_entity(
+    commands: &mut Commands,
+    game_entity: GameEntity,
+    transform: Transform,
+    form_known: bool,
+    befriended: bool,
+) -> Entity {
+    if game_entity.true_form == EntityForm::Enemy {
         commands
             .spawn_bundle(GeometryBuilder::build_as(
                 &build_enemy_geometry(),
@@ -245,24 +245,47 @@ fn spawn_entity(
                     main: Color::DARK_GRAY,
                     outline: Color::ANTIQUE_WHITE,
                 },
-                draw_mode,
-                Transform::from_translation(Vec3::new(position.x, position.y, 10.)),
+                DrawMode::Stroke(
+                    StrokeOptions::default()
+                        .with_line_join(LineJoin::Round)
+                        .with_line_width(3.),
+                ),
+                transform,
             ))
-            .insert(entity);
-        return;
-    }
-    if player_state.level >= entity.true_form.level() {
+            .insert(game_entity)
+            .insert(Collider::Circle(ENTITY_RADIUS))
+            .insert(Enemy::default())
+            .id()
+    } else if befriended {
         commands
             .spawn_bundle(GeometryBuilder::build_as(
-                &entity.true_form.to_shape(),
+                &game_entity.true_form.to_shape(),
+                ShapeColors {
+                    main: Color::LIME_GREEN,
+                    outline: Color::ANTIQUE_WHITE,
+                },
+                DrawMode::Fill(FillOptions::default()),
+                transform,
+            ))
+            .insert(game_entity)
+            .insert(Collider::Circle(ENTITY_RADIUS))
+            .insert(BefriendedEntity)
+            .insert(FormationSlot::default())
+            .id()
+    } else if form_known {
+        commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &game_entity.true_form.to_shape(),
                 ShapeColors {
                     main: Color::AQUAMARINE,
                     outline: Color::ANTIQUE_WHITE,
                 },
-                draw_mode,
-                Transform::from_translation(Vec3::new(position.x, position.y, 10.)),
+                DrawMode::Fill(FillOptions::default()),
+                transform,
             ))
-            .insert(entity);
+            .insert(game_entity)
+            .insert(Collider::Circle(ENTITY_RADIUS))
+            .id()
     } else {
         commands
             .spawn_bundle(GeometryBuilder::build_as(
@@ -274,34 +297,110 @@ fn spawn_entity(
                     main: Color::DARK_GRAY,
                     outline: Color::ANTIQUE_WHITE,
                 },
-                draw_mode,
-                Transform::from_translation(Vec3::new(position.x, position.y, 10.)),
+                DrawMode::Fill(FillOptions::default()),
+                transform,
             ))
-            .insert(entity);
+            .insert(game_entity)
+            .insert(Collider::Circle(ENTITY_RADIUS))
+            .id()
     }
 }
 
+fn spawn_entity(
+    mut commands: Commands,
+    player_state: Res<PlayerState>,
+    mut timer: ResMut<EntityTimer>,
+    mut rng: ResMut<GameRng>,
+    levels: Res<Levels>,
+    tuning: Res<Tuning>,
+    journal: Res<Journal>,
+    clock: Res<GameClock>,
+    game_world: Res<GameWorld>,
+    entities: Query<(&GameEntity, Option<&BefriendedEntity>)>,
+    player_query: Query<&Transform, With<Player>>,
+    field_of_view_query: Query<&FieldOfView>,
+) {
+    if player_state.won || player_state.dead {
+        return;
+    }
+    let level = levels.level(player_state.level);
+    let difficulty = tuning.difficulty(player_state.difficulty);
+    let spawn_interval = Duration::from_secs_f32(level.spawn_interval * difficulty.spawn_interval);
+    if timer.duration() != spawn_interval {
+        timer.set_duration(spawn_interval);
+    }
+    if !timer.tick(clock.delta()).just_finished() {
+        return;
+    }
+    let player_position = player_query
+        .single()
+        .map(|transform| transform.translation.truncate())
+        .unwrap_or(Vec2::ZERO);
+    let field_of_view_height = field_of_view_query
+        .single()
+        .map(|field_of_view| field_of_view.height())
+        .unwrap_or(0.);
+    let position = pick_spawn_position(
+        &mut *rng,
+        &levels.spawn_regions(player_state.level),
+        game_world.border,
+        player_position,
+        field_of_view_height + FIELD_OF_VIEW_MARGIN,
+        tuning.despawn_distance,
+    );
+    let true_form = if rng.gen::<f32>() < difficulty.enemy_fraction(level) {
+        EntityForm::Enemy
+    } else {
+        levels.random_form(&mut *rng)
+    };
+    let entity = GameEntity {
+        known: journal.has_met(&true_form),
+        true_form,
+        current_direction: Vec2::new((2. * rng.gen::<f32>()) - 1., (2. * rng.gen::<f32>()) - 1.)
+            .normalize(),
+        last_contact: clock.elapsed(),
+        last_bump: None,
+        next_direction_change: clock.elapsed()
+            + Duration::from_secs_f32(tuning.min_direction_change),
+        spawned_at: clock.elapsed(),
+        acquaintance: 0.,
+    };
+    let kind = entity.kind(false);
+    let population = entities
+        .iter()
+        .filter(|(game_entity, befriended)| game_entity.kind(befriended.is_some()) == kind)
+        .count();
+    if population >= tuning.population.cap(kind) {
+        return;
+    }
+    let form_known = levels.is_known(&entity.true_form, player_state.level);
+    spawn_game_entity(
+        &mut commands,
+        entity,
+        Transform::from_translation(Vec3::new(position.x, position.y, 10.)),
+        form_known,
+        false,
+    );
+}
+
 fn redraw_after_level_up(
     mut commands: Commands,
     mut level_up_events: EventReader<LevelUpEvent>,
     player_state: Res<PlayerState>,
+    levels: Res<Levels>,
     entities: Query<(Entity, &Transform, &GameEntity), Without<BefriendedEntity>>,
 ) {
     if let Some(_event) = level_up_events.iter().last() {
         for (entity, transform, game_entity) in entities.iter() {
-            if game_entity.true_form.level() == player_state.level {
-                commands.entity(entity).despawn();
-                commands
-                    .spawn_bundle(GeometryBuilder::build_as(
-                        &game_entity.true_form.to_shape(),
-                        ShapeColors {
-                            main: Color::AQUAMARINE,
-                            outline: Color::ANTIQUE_WHITE,
-                        },
-                        DrawMode::Fill(FillOptions::default()),
-                        transform.clone(),
-                    ))
-                    .insert(game_entity.clone());
+            if levels.form_level(&game_entity.true_form) == Some(player_state.level) {
+                commands.entity(entity).despawn_recursive();
+                spawn_game_entity(
+                    &mut commands,
+                    game_entity.clone(),
+                    transform.clone(),
+                    true,
+                    false,
+                );
             }
         }
     }
@@ -309,46 +408,283 @@ fn redraw_after_level_up(
 
 fn move_entities(
     mut entities_query: Query<(&mut Transform, &mut GameEntity)>,
-    time: Res<Time>,
+    tuning: Res<Tuning>,
+    clock: Res<GameClock>,
     game_world: Res<GameWorld>,
 ) {
-    let millis_since_startup = time.time_since_startup().as_millis();
-    let mut random = thread_rng();
     for (mut transform, mut game_entity) in entities_query.iter_mut() {
         transform.translation += Vec3::new(
-            game_entity.current_direction.x * time.delta_seconds() * 100.,
-            game_entity.current_direction.y * time.delta_seconds() * 100.,
+            game_entity.current_direction.x * clock.delta_seconds() * tuning.entity_speed,
+            game_entity.current_direction.y * clock.delta_seconds() * tuning.entity_speed,
             0.,
         );
-        let mut change_direction = false;
         if transform.translation.x > game_world.border
             || transform.translation.x < -game_world.border
             || transform.translation.y > game_world.border
             || transform.translation.y < -game_world.border
         {
             transform.translation -= Vec3::new(
-                game_entity.current_direction.x * time.delta_seconds() * 100.,
-                game_entity.current_direction.y * time.delta_seconds() * 100.,
+                game_entity.current_direction.x * clock.delta_seconds() * tuning.entity_speed,
+                game_entity.current_direction.y * clock.delta_seconds() * tuning.entity_speed,
                 0.,
             );
-            change_direction = true;
+            // bounce off the border and let the steering pick a new direction
+            game_entity.current_direction = -game_entity.current_direction;
+            game_entity.next_direction_change = clock.elapsed();
         }
-        if millis_since_startup >= game_entity.next_direction_change.as_millis() || change_direction
+    }
+}
+
+fn arrange_formation(
+    mut player_state: ResMut<PlayerState>,
+    tuning: Res<Tuning>,
+    player_query: Query<&Transform, With<Player>>,
+    mut friends: Query<(Entity, &Transform, &GameEntity, &mut FormationSlot)>,
+) {
+    let player_transform = if let Ok(player_transform) = player_query.single() {
+        player_transform
+    } else {
+        return;
+    };
+    let player_position = player_transform.translation.truncate();
+    let forward = (player_transform.rotation * Vec3::Y).truncate();
+    let right = Vec2::new(forward.y, -forward.x);
+    let spacing = tuning.formation.spacing;
+    let mut nearby_friends = 0;
+    let mut slots = vec![];
+    for (entity, transform, game_entity, slot) in friends.iter_mut() {
+        if transform.translation.truncate().distance(player_position)
+            < tuning.formation.friend_distance
         {
-            game_entity.current_direction = Vec2::new(
-                (2. * random.gen::<f32>()) - 1.,
-                (2. * random.gen::<f32>()) - 1.,
-            )
-            .normalize();
-            game_entity.next_direction_change = time.time_since_startup()
-                + Duration::from_secs(2)
-                + Duration::from_secs(3).mul_f32(random.gen::<f32>());
+            nearby_friends += 1;
+        }
+        slots.push((game_entity.spawned_at, entity, slot));
+    }
+    player_state.nearby_friends = nearby_friends;
+    // oldest friends walk closest to the player, the others fan out in a V behind them
+    slots.sort_by_key(|(spawned_at, entity, _)| (*spawned_at, *entity));
+    for (index, (_, _, mut slot)) in slots.into_iter().enumerate() {
+        let rank = (index / 2 + 1) as f32;
+        let side = if index % 2 == 0 { -1. } else { 1. };
+        slot.position =
+            player_position - forward * rank * spacing + right * side * rank * spacing * 0.5;
+    }
+}
+
+fn despawn_entities(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    tuning: Res<Tuning>,
+    player_query: Query<&Transform, With<Player>>,
+    entities: Query<(Entity, &Transform, &GameEntity, Option<&BefriendedEntity>)>,
+) {
+    let player_position = if let Ok(player_transform) = player_query.single() {
+        player_transform.translation.truncate()
+    } else {
+        return;
+    };
+    let now = clock.elapsed();
+    let mut befriended = vec![];
+    for (entity, transform, game_entity, befriended_entity) in entities.iter() {
+        let distance = transform.translation.truncate().distance(player_position);
+        let age = now.checked_sub(game_entity.spawned_at).unwrap_or_default();
+        if distance > tuning.despawn_distance
+            || (distance > VISIBLE_DISTANCE && age > tuning.max_entity_age())
+        {
+            commands.entity(entity).despawn_recursive();
+        } else if befriended_entity.is_some() {
+            befriended.push((game_entity.spawned_at, entity));
+        }
+    }
+    let cap = tuning.population.cap(EntityKind::Befriended);
+    if befriended.len() > cap {
+        befriended.sort_by_key(|(spawned_at, _)| *spawned_at);
+        let excess = befriended.len() - cap;
+        for (_, entity) in befriended.into_iter().take(excess) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub struct AcquaintanceRing {
+    progress: f32,
+}
+
+const ACQUAINTANCE_RING_RADIUS: f32 = ENTITY_RADIUS + 6.;
+
+fn build_acquaintance_ring(progress: f32) -> impl Geometry {
+    let mut builder = PathBuilder::new();
+    builder.move_to(Vec2::new(0., ACQUAINTANCE_RING_RADIUS));
+    builder.arc(
+        Vec2::ZERO,
+        Vec2::new(ACQUAINTANCE_RING_RADIUS, ACQUAINTANCE_RING_RADIUS),
+        -progress * 2. * PI,
+        0.,
+    );
+    builder.build()
+}
+
+fn build_acquaintance_ring_bundle(progress: f32) -> ShapeBundle {
+    GeometryBuilder::build_as(
+        &build_acquaintance_ring(progress),
+        ShapeColors {
+            main: Color::LIME_GREEN,
+            outline: Color::LIME_GREEN,
+        },
+        DrawMode::Stroke(
+            StrokeOptions::default()
+                .with_line_cap(LineCap::Round)
+                .with_line_width(4.),
+        ),
+        Transform::from_translation(Vec3::new(0., 0., 1.)),
+    )
+}
+
+// Rings are children of their entity, so they follow it and are despawned together with it
+fn draw_acquaintance_rings(
+    mut commands: Commands,
+    mut rings: Query<&mut AcquaintanceRing>,
+    entities: Query<(Entity, &GameEntity, Option<&Children>), Without<BefriendedEntity>>,
+) {
+    for (entity, game_entity, children) in entities.iter() {
+        let progress = game_entity.acquaintance;
+        let ring = children.and_then(|children| {
+            children
+                .iter()
+                .find(|child| rings.get_mut(**child).is_ok())
+                .copied()
+        });
+        let visible = progress > 0. && progress < 1.;
+        match ring {
+            Some(ring) if !visible => commands.entity(ring).despawn_recursive(),
+            Some(ring) => {
+                let mut acquaintance_ring = match rings.get_mut(ring) {
+                    Ok(acquaintance_ring) => acquaintance_ring,
+                    Err(_) => continue,
+                };
+                if acquaintance_ring.progress != progress {
+                    acquaintance_ring.progress = progress;
+                    // a removed and inserted path counts as added and gets tessellated again
+                    commands
+                        .entity(ring)
+                        .remove::<Path>()
+                        .insert(build_acquaintance_ring_bundle(progress).path);
+                }
+            }
+            None if visible => {
+                let ring = commands
+                    .spawn_bundle(build_acquaintance_ring_bundle(progress))
+                    .insert(AcquaintanceRing { progress })
+                    .id();
+                commands.entity(entity).push_children(&[ring]);
+            }
+            None => (),
         }
     }
 }
 
+// Faint outline of the true form on unknown circles the player has met before
+pub struct Hint {
+    entity: Entity,
+}
+
+fn update_hints(
+    mut commands: Commands,
+    player_state: Res<PlayerState>,
+    levels: Res<Levels>,
+    mut hints: Query<(Entity, &Hint, &mut Transform)>,
+    entities: Query<(Entity, &Transform, &GameEntity), (Without<Hint>, Without<BefriendedEntity>)>,
+) {
+    let needs_hint = |game_entity: &GameEntity| {
+        game_entity.known
+            && game_entity.true_form != EntityForm::Enemy
+            && !levels.is_known(&game_entity.true_form, player_state.level)
+    };
+    let mut hinted = HashSet::default();
+    for (hint_entity, hint, mut hint_transform) in hints.iter_mut() {
+        match entities.get(hint.entity) {
+            Ok((_, transform, game_entity)) if needs_hint(game_entity) => {
+                hint_transform.translation.x = transform.translation.x;
+                hint_transform.translation.y = transform.translation.y;
+                hinted.insert(hint.entity);
+            }
+            _ => commands.entity(hint_entity).despawn(),
+        }
+    }
+    for (entity, transform, game_entity) in entities.iter() {
+        if !needs_hint(game_entity) || hinted.contains(&entity) {
+            continue;
+        }
+        commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &game_entity.true_form.to_shape(),
+                ShapeColors {
+                    main: Color::rgba(1., 1., 1., 0.4),
+                    outline: Color::rgba(1., 1., 1., 0.4),
+                },
+                DrawMode::Stroke(StrokeOptions::default().with_line_width(2.)),
+                Transform {
+                    translation: Vec3::new(transform.translation.x, transform.translation.y, 11.),
+                    scale: Vec3::new(0.6, 0.6, 1.),
+                    ..Default::default()
+                },
+            ))
+            .insert(Hint { entity });
+    }
+}
+
+fn remove_hints(mut commands: Commands, hints: Query<Entity, With<Hint>>) {
+    for hint in hints.iter() {
+        commands.entity(hint).despawn();
+    }
+}
+
 fn remove_entities(mut commands: Commands, entity_query: Query<Entity, With<GameEntity>>) {
     for entity in entity_query.iter() {
-        commands.entity(entity).despawn();
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_secs(1);
+
+    fn game_entity(true_form: EntityForm) -> GameEntity {
+        GameEntity {
+            true_form,
+            current_direction: Vec2::new(1., 0.),
+            last_contact: Duration::from_secs(0),
+            last_bump: None,
+            next_direction_change: Duration::from_secs(0),
+            spawned_at: Duration::from_secs(0),
+            known: true,
+            acquaintance: 0.,
+        }
+    }
+
+    #[test]
+    fn bumps_wait_for_the_cooldown() {
+        let mut enemy = game_entity(EntityForm::Enemy);
+        assert!(enemy.bump(Duration::from_secs(10), COOLDOWN, false));
+        assert!(!enemy.bump(Duration::from_millis(10_500), COOLDOWN, false));
+        assert!(enemy.bump(Duration::from_secs(12), COOLDOWN, false));
+    }
+
+    #[test]
+    fn friends_only_give_courage_on_the_first_bump() {
+        let mut friend = game_entity(EntityForm::Rectangle);
+        assert!(friend.bump(Duration::from_secs(10), COOLDOWN, true));
+        assert!(!friend.bump(Duration::from_secs(20), COOLDOWN, true));
+        assert!(!friend.bump(Duration::from_secs(300), COOLDOWN, true));
+    }
+
+    #[test]
+    fn bumps_do_not_touch_the_nope_cooldown() {
+        let mut entity = game_entity(EntityForm::Pentagon);
+        entity.last_contact = Duration::from_secs(3);
+        entity.bump(Duration::from_secs(10), COOLDOWN, false);
+        assert_eq!(entity.last_contact, Duration::from_secs(3));
     }
 }
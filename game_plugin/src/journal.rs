@@ -0,0 +1,115 @@
+use crate::entities::EntityForm;
+use crate::player::{DyingEvent, FriendEvent, MeetEvent, WonEvent};
+use crate::storage;
+use crate::GameState;
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::window::WindowCloseRequested;
+use serde::{Deserialize, Serialize};
+
+const JOURNAL_FILE: &str = "journal.ron";
+
+pub struct JournalPlugin;
+
+impl Plugin for JournalPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Journal::load())
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(record_meetings.system())
+                    .with_system(record_befriended.system())
+                    .with_system(save_journal_on_end.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Playing).with_system(save_journal.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Paused).with_system(save_journal.system()),
+            )
+            // closing the window exits without leaving `Playing` or pausing first
+            .add_system_to_stage(CoreStage::PostUpdate, save_journal_on_exit.system());
+    }
+}
+
+// Every form the player has met so far, over all runs
+#[derive(Serialize, Deserialize, Default)]
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub form: EntityForm,
+    pub befriended: u32,
+}
+
+impl Journal {
+    fn load() -> Self {
+        storage::data_file(JOURNAL_FILE)
+            .and_then(|path| storage::load(&path))
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        if let Some(path) = storage::data_file(JOURNAL_FILE) {
+            storage::save(&path, self);
+        }
+    }
+
+    pub fn entry(&self, form: &EntityForm) -> Option<&JournalEntry> {
+        self.entries.iter().find(|entry| &entry.form == form)
+    }
+
+    pub fn has_met(&self, form: &EntityForm) -> bool {
+        self.entry(form).is_some()
+    }
+
+    fn entry_mut(&mut self, form: &EntityForm) -> &mut JournalEntry {
+        if let Some(index) = self.entries.iter().position(|entry| &entry.form == form) {
+            return &mut self.entries[index];
+        }
+        info!("Met a {:?} for the first time", form);
+        self.entries.push(JournalEntry {
+            form: form.clone(),
+            befriended: 0,
+        });
+        self.entries.last_mut().unwrap()
+    }
+}
+
+fn record_meetings(mut journal: ResMut<Journal>, mut meet_events: EventReader<MeetEvent>) {
+    for event in meet_events.iter() {
+        journal.entry_mut(&event.form);
+    }
+}
+
+// Counted by event, so entities restored from a saved run are not counted twice
+fn record_befriended(mut journal: ResMut<Journal>, mut friend_events: EventReader<FriendEvent>) {
+    for event in friend_events.iter() {
+        journal.entry_mut(&event.form).befriended += 1;
+    }
+}
+
+fn save_journal_on_end(
+    journal: Res<Journal>,
+    mut dying_events: EventReader<DyingEvent>,
+    mut won_events: EventReader<WonEvent>,
+) {
+    if dying_events.iter().last().is_some() || won_events.iter().last().is_some() {
+        journal.save();
+    }
+}
+
+fn save_journal_on_exit(
+    journal: Res<Journal>,
+    mut exit_events: EventReader<AppExit>,
+    mut close_events: EventReader<WindowCloseRequested>,
+) {
+    if exit_events.iter().last().is_some() || close_events.iter().last().is_some() {
+        journal.save();
+    }
+}
+
+fn save_journal(journal: Res<Journal>) {
+    journal.save();
+}
@@ -0,0 +1,315 @@
+use crate::entities::EntityForm;
+use crate::loading::DataAssets;
+use crate::spawn::SpawnRegion;
+use crate::tuning::Tuning;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::BoxedFuture;
+use rand::Rng;
+use serde::Deserialize;
+
+pub struct LevelsPlugin;
+
+impl Plugin for LevelsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<Levels>()
+            .init_asset_loader::<LevelsLoader>()
+            .add_system(reload_levels.system());
+    }
+}
+
+#[derive(Deserialize, TypeUuid, Clone)]
+#[uuid = "43a9579d-9f44-406f-b33c-e3cc6958a0d6"]
+pub struct Levels {
+    initial_entities: usize,
+    win_level: usize,
+    #[serde(default)]
+    spawn_regions: Vec<SpawnRegion>,
+    levels: Vec<Level>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Level {
+    pub form: EntityForm,
+    pub initial_count: usize,
+    // Names of the spawn regions used in this level. Entities spawn anywhere if empty.
+    #[serde(default)]
+    pub spawn_regions: Vec<String>,
+    pub starting_courage: f32,
+    pub befriend_courage: f32,
+    pub nope_courage: f32,
+    pub spawn_interval: f32,
+    pub enemy_fraction: f32,
+}
+
+impl Levels {
+    pub fn level(&self, level: usize) -> &Level {
+        &self.levels[level.min(self.levels.len() - 1)]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Level> {
+        self.levels.iter()
+    }
+
+    pub fn form_level(&self, form: &EntityForm) -> Option<usize> {
+        self.levels.iter().position(|level| &level.form == form)
+    }
+
+    pub fn is_known(&self, form: &EntityForm, player_level: usize) -> bool {
+        self.form_level(form)
+            .map_or(false, |form_level| form_level <= player_level)
+    }
+
+    pub fn spawn_regions(&self, level: usize) -> Vec<&SpawnRegion> {
+        self.level(level)
+            .spawn_regions
+            .iter()
+            .filter_map(|name| {
+                self.spawn_regions
+                    .iter()
+                    .find(|region| &region.name == name)
+            })
+            .collect()
+    }
+
+    pub fn win_level(&self) -> usize {
+        self.win_level.min(self.levels.len())
+    }
+
+    pub fn has_won(&self, player_level: usize) -> bool {
+        player_level >= self.win_level()
+    }
+
+    pub fn random_form<R: Rng + ?Sized>(&self, rng: &mut R) -> EntityForm {
+        self.levels[rng.gen_range(0..self.levels.len())]
+            .form
+            .clone()
+    }
+
+    // Forms of all entities spawned at the start of a run
+    pub fn initial_forms<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<EntityForm> {
+        let mut forms: Vec<EntityForm> = self
+            .levels
+            .iter()
+            .flat_map(|level| std::iter::repeat(level.form.clone()).take(level.initial_count))
+            .collect();
+        while forms.len() < self.initial_entities {
+            forms.push(self.random_form(rng));
+        }
+        forms
+    }
+
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.levels.is_empty() {
+            return Err(anyhow::anyhow!("At least one level is required"));
+        }
+        if self.levels.iter().any(|level| level.spawn_interval <= 0.) {
+            return Err(anyhow::anyhow!("Spawn intervals have to be positive"));
+        }
+        if self
+            .levels
+            .iter()
+            .any(|level| level.starting_courage <= 0. || level.befriend_courage <= 0.)
+        {
+            return Err(anyhow::anyhow!(
+                "Starting and befriend courage have to be positive"
+            ));
+        }
+        if self
+            .levels
+            .iter()
+            .any(|level| !(0. ..=1.).contains(&level.enemy_fraction))
+        {
+            return Err(anyhow::anyhow!(
+                "Enemy fractions have to be between 0 and 1"
+            ));
+        }
+        // spawned entities take their form from any level, so every form has to be befriendable
+        if self
+            .levels
+            .iter()
+            .any(|level| level.form == EntityForm::Enemy)
+        {
+            return Err(anyhow::anyhow!("Levels cannot use the enemy form"));
+        }
+        // a level that spawns nothing but enemies could never be left
+        if let Some(index) = self.levels[..self.win_level()]
+            .iter()
+            .position(|level| level.enemy_fraction >= 1.)
+        {
+            return Err(anyhow::anyhow!(
+                "Level {} only spawns enemies and cannot be won",
+                index
+            ));
+        }
+        if let Some(region) = self.spawn_regions.iter().find(|region| !region.is_valid()) {
+            return Err(anyhow::anyhow!("Spawn region '{}' is empty", region.name));
+        }
+        for level in self.levels.iter() {
+            for name in level.spawn_regions.iter() {
+                if !self.spawn_regions.iter().any(|region| &region.name == name) {
+                    return Err(anyhow::anyhow!("Unknown spawn region '{}'", name));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        ron::from_str(include_str!("../../assets/data/campaign.levels"))
+            .expect("Failed to parse the bundled level definitions")
+    }
+}
+
+#[derive(Default)]
+pub struct LevelsLoader;
+
+impl AssetLoader for LevelsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let levels: Levels = ron::de::from_bytes(bytes)?;
+            levels.validate()?;
+            load_context.set_default_asset(LoadedAsset::new(levels));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["levels"]
+    }
+}
+
+fn reload_levels(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<Levels>>,
+    levels: Res<Assets<Levels>>,
+    tuning: Option<Res<Tuning>>,
+    data_assets: Option<Res<DataAssets>>,
+) {
+    let data_assets = if let Some(data_assets) = data_assets {
+        data_assets
+    } else {
+        return;
+    };
+    for event in events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            if handle != &data_assets.levels {
+                continue;
+            }
+            if let Some(levels) = levels.get(handle) {
+                if let Some(Err(error)) = tuning.as_ref().map(|tuning| tuning.validate_with(levels))
+                {
+                    error!("Ignoring reloaded level definitions: {}", error);
+                    continue;
+                }
+                info!("Reloaded level definitions");
+                commands.insert_resource(levels.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(first_level: &str) -> Result<(), anyhow::Error> {
+        let levels: Levels = ron::from_str(&format!(
+            r#"(
+                initial_entities: 0,
+                win_level: 2,
+                spawn_regions: [(name: "center", min: (-10.0, -10.0), max: (10.0, 10.0))],
+                levels: [
+                    {},
+                    (form: Pentagon, initial_count: 0, starting_courage: 25.0,
+                        befriend_courage: 20.0, nope_courage: 10.0, spawn_interval: 2.2,
+                        enemy_fraction: 0.8),
+                ],
+            )"#,
+            first_level
+        ))
+        .unwrap();
+        levels.validate()
+    }
+
+    #[test]
+    fn accepts_valid_levels() {
+        assert!(levels(
+            r#"(form: Rectangle, initial_count: 0, spawn_regions: ["center"],
+                starting_courage: 50.0, befriend_courage: 20.0, nope_courage: 20.0,
+                spawn_interval: 2.2, enemy_fraction: 0.8)"#
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn bundled_levels_are_valid() {
+        assert!(Levels::default().validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_enemy_fractions_outside_of_zero_to_one() {
+        for enemy_fraction in ["-0.1", "1.2"].iter() {
+            assert!(levels(&format!(
+                r#"(form: Rectangle, initial_count: 0, starting_courage: 50.0,
+                    befriend_courage: 20.0, nope_courage: 20.0, spawn_interval: 2.2,
+                    enemy_fraction: {})"#,
+                enemy_fraction
+            ))
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_courage_that_is_not_positive() {
+        assert!(levels(
+            r#"(form: Rectangle, initial_count: 0, starting_courage: 0.0,
+                befriend_courage: 20.0, nope_courage: 20.0, spawn_interval: 2.2,
+                enemy_fraction: 0.8)"#
+        )
+        .is_err());
+        assert!(levels(
+            r#"(form: Rectangle, initial_count: 0, starting_courage: 50.0,
+                befriend_courage: -5.0, nope_courage: 20.0, spawn_interval: 2.2,
+                enemy_fraction: 0.8)"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_levels_that_only_spawn_enemies() {
+        assert!(levels(
+            r#"(form: Rectangle, initial_count: 5, starting_courage: 50.0,
+                befriend_courage: 20.0, nope_courage: 20.0, spawn_interval: 2.2,
+                enemy_fraction: 1.0)"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_the_enemy_form() {
+        assert!(levels(
+            r#"(form: Enemy, initial_count: 0, starting_courage: 50.0,
+                befriend_courage: 20.0, nope_courage: 20.0, spawn_interval: 2.2,
+                enemy_fraction: 0.8)"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_spawn_regions() {
+        assert!(levels(
+            r#"(form: Rectangle, initial_count: 0, spawn_regions: ["corner"],
+                starting_courage: 50.0, befriend_courage: 20.0, nope_courage: 20.0,
+                spawn_interval: 2.2, enemy_fraction: 0.8)"#
+        )
+        .is_err());
+    }
+}
@@ -1,30 +1,78 @@
 mod actions;
+#[cfg(feature = "audio")]
 mod audio;
+mod behaviour;
+mod clock;
+mod collision;
+mod courage;
+mod difficulty;
+mod enemies;
 mod entities;
+mod journal;
+mod levels;
 mod loading;
 mod menu;
+mod pause;
 mod player;
+mod replay;
+mod rng;
+mod savegame;
+mod settings;
+mod spatial;
+mod spawn;
+mod stats;
+mod storage;
+mod tuning;
 mod ui;
+mod visibility;
 
 use crate::actions::ActionsPlugin;
+#[cfg(feature = "audio")]
 use crate::audio::InternalAudioPlugin;
+use crate::behaviour::BehaviourPlugin;
+use crate::clock::ClockPlugin;
+use crate::collision::CollisionPlugin;
+use crate::enemies::EnemiesPlugin;
+use crate::journal::{Journal, JournalPlugin};
+use crate::levels::{Levels, LevelsPlugin};
 use crate::loading::{LoadingPlugin, TextureAssets};
 use crate::player::{PlayerCamera, PlayerPlugin};
+use crate::replay::ReplayPlugin;
+use crate::rng::RngPlugin;
+use crate::savegame::{ContinuedRun, SaveGamePlugin};
+use crate::settings::{Settings, SettingsPlugin};
+use crate::spatial::SpatialPlugin;
+use crate::stats::StatsPlugin;
+use crate::tuning::{Tuning, TuningPlugin};
+use crate::visibility::VisibilityPlugin;
 
 use bevy::app::AppBuilder;
+use bevy::input::InputPlugin;
+use bevy::window::WindowPlugin;
 // use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use crate::entities::EntitiesPlugin;
 use crate::menu::MenuPlugin;
+use crate::pause::PausePlugin;
 use crate::ui::UiPlugin;
 use bevy::prelude::*;
 use bevy_prototype_lyon::prelude::ShapePlugin;
 
+pub use crate::actions::Actions;
+pub use crate::clock::{ClockSystemLabels, GameClock};
+pub use crate::entities::{EntityForm, GameEntity};
+pub use crate::player::{Player, PlayerState};
+pub use crate::rng::GameRng;
+
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 enum GameState {
     Loading,
     Playing,
     RenderBackground,
     Menu,
+    Journal,
+    Settings,
+    Results,
+    Paused,
     Restart,
 }
 
@@ -34,17 +82,37 @@ pub struct GameWorld {
     border: f32,
 }
 
+impl Default for GameWorld {
+    fn default() -> Self {
+        GameWorld { border: 980. }
+    }
+}
+
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.add_state(GameState::Loading)
-            .insert_resource(GameWorld { border: 980. })
+            .init_resource::<GameWorld>()
             .add_plugin(ShapePlugin)
+            .add_plugin(LevelsPlugin)
+            .add_plugin(TuningPlugin)
+            .add_plugin(SettingsPlugin)
+            .add_plugin(ClockPlugin)
+            .add_plugin(RngPlugin)
+            .add_plugin(ReplayPlugin { from_env: true })
             .add_plugin(EntitiesPlugin)
+            .add_plugin(BehaviourPlugin)
+            .add_plugin(EnemiesPlugin)
+            .add_plugin(SpatialPlugin)
+            .add_plugin(CollisionPlugin)
+            .add_plugin(VisibilityPlugin)
+            .add_plugin(JournalPlugin)
+            .add_plugin(SaveGamePlugin)
+            .add_plugin(StatsPlugin)
             .add_plugin(MenuPlugin)
+            .add_plugin(PausePlugin)
             .add_plugin(UiPlugin)
             .add_plugin(LoadingPlugin)
             .add_plugin(ActionsPlugin)
-            .add_plugin(InternalAudioPlugin)
             .add_plugin(PlayerPlugin)
             .add_system_set(
                 SystemSet::on_enter(GameState::Restart).with_system(switch_to_game.system()),
@@ -53,11 +121,46 @@ impl Plugin for GamePlugin {
                 SystemSet::on_enter(GameState::RenderBackground)
                     .with_system(spawn_camera_and_background.system()),
             );
+        #[cfg(feature = "audio")]
+        app.add_plugin(InternalAudioPlugin);
         // .add_plugin(FrameTimeDiagnosticsPlugin::default())
         // .add_plugin(LogDiagnosticsPlugin::default())
     }
 }
 
+// Runs the gameplay without window, rendering or audio. Add it together with `MinimalPlugins`.
+// Runs are always live here, recording and replaying through the environment is left to the game.
+// Build with `--no-default-features` to leave out the window, GPU and audio backends.
+pub struct HeadlessGamePlugin;
+
+impl Plugin for HeadlessGamePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_state(GameState::Playing)
+            .add_plugin(InputPlugin::default())
+            .add_plugin(WindowPlugin {
+                add_primary_window: false,
+                exit_on_close: false,
+            })
+            .init_resource::<GameWorld>()
+            .init_resource::<Levels>()
+            .init_resource::<Tuning>()
+            .init_resource::<Journal>()
+            .init_resource::<Settings>()
+            .init_resource::<ContinuedRun>()
+            .add_plugin(ClockPlugin)
+            .add_plugin(RngPlugin)
+            .add_plugin(ReplayPlugin { from_env: false })
+            .add_plugin(EntitiesPlugin)
+            .add_plugin(BehaviourPlugin)
+            .add_plugin(EnemiesPlugin)
+            .add_plugin(SpatialPlugin)
+            .add_plugin(CollisionPlugin)
+            .add_plugin(VisibilityPlugin)
+            .add_plugin(ActionsPlugin)
+            .add_plugin(PlayerPlugin);
+    }
+}
+
 fn switch_to_game(mut state: ResMut<State<GameState>>) {
     state.set(GameState::Playing).unwrap();
 }
@@ -1,9 +1,12 @@
 mod paths;
 
+use crate::levels::Levels;
 use crate::loading::paths::PATHS;
+use crate::tuning::Tuning;
 use crate::GameState;
 use bevy::asset::LoadState;
 use bevy::prelude::*;
+#[cfg(feature = "audio")]
 use bevy_kira_audio::AudioSource;
 
 pub struct LoadingPlugin;
@@ -21,12 +24,14 @@ pub struct LoadingState {
     textures: Vec<HandleUntyped>,
     fonts: Vec<HandleUntyped>,
     audio: Vec<HandleUntyped>,
+    data: Vec<HandleUntyped>,
 }
 
 pub struct FontAssets {
     pub fira_sans: Handle<Font>,
 }
 
+#[cfg(feature = "audio")]
 pub struct AudioAssets {
     pub background: Handle<AudioSource>,
     pub hi_1: Handle<AudioSource>,
@@ -38,6 +43,13 @@ pub struct AudioAssets {
     pub level_up: Handle<AudioSource>,
     pub lets_go: Handle<AudioSource>,
     pub won: Handle<AudioSource>,
+    pub enemy_chase: Handle<AudioSource>,
+    pub enemy_repelled: Handle<AudioSource>,
+}
+
+pub struct DataAssets {
+    pub levels: Handle<Levels>,
+    pub tuning: Handle<Tuning>,
 }
 
 pub struct TextureAssets {
@@ -46,29 +58,48 @@ pub struct TextureAssets {
 }
 
 fn start_loading(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // hot reloading of the tuning and levels is only meant for development builds
+    #[cfg(debug_assertions)]
+    if let Err(error) = asset_server.watch_for_changes() {
+        error!("Failed to watch assets for changes: {:?}", error);
+    }
+
     let mut fonts: Vec<HandleUntyped> = vec![];
     fonts.push(asset_server.load_untyped(PATHS.fira_sans));
 
-    let mut audio: Vec<HandleUntyped> = vec![];
-    audio.push(asset_server.load_untyped(PATHS.audio_background));
-    audio.push(asset_server.load_untyped(PATHS.audio_hi_1));
-    audio.push(asset_server.load_untyped(PATHS.audio_hi_2));
-    audio.push(asset_server.load_untyped(PATHS.audio_hi_3));
-    audio.push(asset_server.load_untyped(PATHS.audio_nope_1));
-    audio.push(asset_server.load_untyped(PATHS.audio_nope_2));
-    audio.push(asset_server.load_untyped(PATHS.audio_dying));
-    audio.push(asset_server.load_untyped(PATHS.audio_level_up));
-    audio.push(asset_server.load_untyped(PATHS.audio_lets_go));
-    audio.push(asset_server.load_untyped(PATHS.audio_won));
+    // there is no loader for the sound files without the audio feature
+    let audio: Vec<HandleUntyped> = if cfg!(feature = "audio") {
+        vec![
+            asset_server.load_untyped(PATHS.audio_background),
+            asset_server.load_untyped(PATHS.audio_hi_1),
+            asset_server.load_untyped(PATHS.audio_hi_2),
+            asset_server.load_untyped(PATHS.audio_hi_3),
+            asset_server.load_untyped(PATHS.audio_nope_1),
+            asset_server.load_untyped(PATHS.audio_nope_2),
+            asset_server.load_untyped(PATHS.audio_dying),
+            asset_server.load_untyped(PATHS.audio_level_up),
+            asset_server.load_untyped(PATHS.audio_lets_go),
+            asset_server.load_untyped(PATHS.audio_won),
+            asset_server.load_untyped(PATHS.audio_enemy_chase),
+            asset_server.load_untyped(PATHS.audio_enemy_repelled),
+        ]
+    } else {
+        vec![]
+    };
 
     let mut textures: Vec<HandleUntyped> = vec![];
     textures.push(asset_server.load_untyped(PATHS.texture_background));
     textures.push(asset_server.load_untyped(PATHS.texture_menu));
 
+    let mut data: Vec<HandleUntyped> = vec![];
+    data.push(asset_server.load_untyped(PATHS.levels));
+    data.push(asset_server.load_untyped(PATHS.tuning));
+
     commands.insert_resource(LoadingState {
         textures,
         fonts,
         audio,
+        data,
     });
 }
 
@@ -77,6 +108,8 @@ fn check_state(
     mut state: ResMut<State<GameState>>,
     asset_server: Res<AssetServer>,
     loading_state: Res<LoadingState>,
+    levels: Res<Assets<Levels>>,
+    tunings: Res<Assets<Tuning>>,
 ) {
     if LoadState::Loaded
         != asset_server.get_group_load_state(loading_state.fonts.iter().map(|handle| handle.id))
@@ -93,11 +126,17 @@ fn check_state(
     {
         return;
     }
+    if LoadState::Loaded
+        != asset_server.get_group_load_state(loading_state.data.iter().map(|handle| handle.id))
+    {
+        return;
+    }
 
     commands.insert_resource(FontAssets {
         fira_sans: asset_server.get_handle(PATHS.fira_sans),
     });
 
+    #[cfg(feature = "audio")]
     commands.insert_resource(AudioAssets {
         background: asset_server.get_handle(PATHS.audio_background),
         hi_1: asset_server.get_handle(PATHS.audio_hi_1),
@@ -109,6 +148,8 @@ fn check_state(
         level_up: asset_server.get_handle(PATHS.audio_level_up),
         lets_go: asset_server.get_handle(PATHS.audio_lets_go),
         won: asset_server.get_handle(PATHS.audio_won),
+        enemy_chase: asset_server.get_handle(PATHS.audio_enemy_chase),
+        enemy_repelled: asset_server.get_handle(PATHS.audio_enemy_repelled),
     });
 
     commands.insert_resource(TextureAssets {
@@ -116,5 +157,20 @@ fn check_state(
         menu: asset_server.get_handle(PATHS.texture_menu),
     });
 
+    let levels_handle = asset_server.get_handle(PATHS.levels);
+    let tuning_handle = asset_server.get_handle(PATHS.tuning);
+    let levels = levels.get(&levels_handle).unwrap().clone();
+    let tuning = tunings.get(&tuning_handle).unwrap().clone();
+    // both were valid on their own, so the game starts anyway
+    if let Err(error) = tuning.validate_with(&levels) {
+        error!("Levels and tuning do not fit together: {}", error);
+    }
+    commands.insert_resource(levels);
+    commands.insert_resource(tuning);
+    commands.insert_resource(DataAssets {
+        levels: levels_handle,
+        tuning: tuning_handle,
+    });
+
     state.set(GameState::RenderBackground).unwrap();
 }
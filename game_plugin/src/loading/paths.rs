@@ -10,8 +10,12 @@ pub struct AssetPaths {
     pub audio_level_up: &'static str,
     pub audio_lets_go: &'static str,
     pub audio_won: &'static str,
+    pub audio_enemy_chase: &'static str,
+    pub audio_enemy_repelled: &'static str,
     pub texture_background: &'static str,
     pub texture_menu: &'static str,
+    pub levels: &'static str,
+    pub tuning: &'static str,
 }
 
 pub const PATHS: AssetPaths = AssetPaths {
@@ -26,6 +30,10 @@ pub const PATHS: AssetPaths = AssetPaths {
     audio_level_up: "audio/level_up.ogg",
     audio_lets_go: "audio/lets_go.ogg",
     audio_won: "audio/won.ogg",
+    audio_enemy_chase: "audio/enemy_chase.wav",
+    audio_enemy_repelled: "audio/enemy_repelled.wav",
     texture_background: "textures/background.png",
     texture_menu: "textures/menu.png",
+    levels: "data/campaign.levels",
+    tuning: "data/balance.tuning",
 };
@@ -1,5 +1,13 @@
+use crate::actions::GameControl;
+use crate::entities::EntityForm;
+use crate::journal::Journal;
+use crate::levels::Levels;
 use crate::loading::{FontAssets, TextureAssets};
+use crate::savegame::{ContinuedRun, SavedRun};
+use crate::settings::{Settings, VolumeChannel, VOLUME_STEPS};
+use crate::stats::{RunHistory, RunStats};
 use crate::GameState;
+use bevy::ecs::component::Component;
 use bevy::prelude::*;
 use bevy_prototype_lyon::prelude::{shapes, DrawMode, FillOptions, GeometryBuilder, ShapeColors};
 
@@ -8,19 +16,60 @@ pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.init_resource::<ButtonMaterials>()
+            .init_resource::<Rebinding>()
             .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(setup_menu.system()))
             .add_system_set(
-                SystemSet::on_update(GameState::Menu).with_system(click_play_button.system()),
+                SystemSet::on_update(GameState::Menu)
+                    .with_system(click_continue_button.system())
+                    .with_system(click_play_button.system())
+                    .with_system(click_difficulty_button.system())
+                    .with_system(click_journal_button.system())
+                    .with_system(click_settings_button.system())
+                    .with_system(click_results_button.system()),
             )
-            .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(remove_menu.system()));
+            .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(remove_menu.system()))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Journal).with_system(setup_journal.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Journal).with_system(click_back_button.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Journal).with_system(remove_journal.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Settings).with_system(setup_settings.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Settings)
+                    .with_system(click_back_button.system())
+                    .with_system(click_volume_segment.system())
+                    .with_system(click_fullscreen_button.system())
+                    .with_system(click_key_binding_button.system())
+                    .with_system(capture_key_binding.system())
+                    .with_system(update_settings_screen.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Settings).with_system(remove_settings.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Results).with_system(setup_results.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Results).with_system(click_back_button.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Results).with_system(remove_results.system()),
+            );
     }
 }
 
 struct Menu;
 
-struct ButtonMaterials {
-    normal: Handle<ColorMaterial>,
-    hovered: Handle<ColorMaterial>,
+pub struct ButtonMaterials {
+    pub normal: Handle<ColorMaterial>,
+    pub hovered: Handle<ColorMaterial>,
+    pub selected: Handle<ColorMaterial>,
 }
 
 impl FromWorld for ButtonMaterials {
@@ -29,19 +78,47 @@ impl FromWorld for ButtonMaterials {
         ButtonMaterials {
             normal: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
             hovered: materials.add(Color::rgb(0.25, 0.25, 0.25).into()),
+            selected: materials.add(Color::rgb(0.6, 0.6, 0.6).into()),
         }
     }
 }
 
+struct ContinueButton;
 struct PlayButton;
+struct DifficultyButton;
+struct JournalButton;
+
+struct SettingsButton;
+struct ResultsButton;
+
+struct JournalScreen;
+struct BackButton;
+
+struct SettingsScreen;
+
+struct ResultsScreen;
+const LEADERBOARD_SIZE: usize = 5;
+struct FullscreenButton;
+struct KeyBindingButton(GameControl);
+
+struct VolumeSegment {
+    channel: VolumeChannel,
+    step: usize,
+}
+
+// The control waiting for a new key, if any
+#[derive(Default)]
+struct Rebinding(Option<GameControl>);
 
 fn setup_menu(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
     texture_assets: Res<TextureAssets>,
     button_materials: Res<ButtonMaterials>,
+    settings: Res<Settings>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
+    let row_material = materials.add(Color::NONE.into());
     let shape = shapes::RegularPolygon {
         sides: 3,
         feature: shapes::RegularPolygonFeature::Radius(40.0),
@@ -58,12 +135,109 @@ fn setup_menu(
             Transform::from_translation(Vec3::new(0., 150., 20.)),
         ))
         .insert(Menu);
-    commands.spawn_bundle(UiCameraBundle::default());
     commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(Menu);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .insert(Menu)
+        .with_children(|parent| {
+            if SavedRun::exists() {
+                spawn_button(
+                    parent,
+                    &font_assets,
+                    &button_materials,
+                    "Continue",
+                    ContinueButton,
+                );
+            }
+            spawn_button(parent, &font_assets, &button_materials, "Play", PlayButton);
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    material: row_material.clone(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Difficulty",
+                            TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 30.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
+                    });
+                    spawn_button(
+                        parent,
+                        &font_assets,
+                        &button_materials,
+                        &format!("{:?}", settings.difficulty),
+                        DifficultyButton,
+                    );
+                });
+            spawn_button(
+                parent,
+                &font_assets,
+                &button_materials,
+                "Journal",
+                JournalButton,
+            );
+            spawn_button(
+                parent,
+                &font_assets,
+                &button_materials,
+                "Settings",
+                SettingsButton,
+            );
+            spawn_button(
+                parent,
+                &font_assets,
+                &button_materials,
+                "Best runs",
+                ResultsButton,
+            );
+        });
+
+    let mut menu_transform = Transform::from_translation(Vec3::new(50., -150., 10.));
+    menu_transform.scale = Vec3::new(0.4, 0.4, 0.4);
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(texture_assets.menu.clone().into()),
+            transform: menu_transform,
+            ..Default::default()
+        })
+        .insert(Menu);
+}
+
+pub fn spawn_button<T: Component>(
+    parent: &mut ChildBuilder,
+    font_assets: &FontAssets,
+    button_materials: &ButtonMaterials,
+    label: &str,
+    marker: T,
+) {
+    parent
         .spawn_bundle(ButtonBundle {
             style: Style {
-                size: Size::new(Val::Px(120.0), Val::Px(50.0)),
-                margin: Rect::all(Val::Auto),
+                size: Size::new(Val::Px(160.0), Val::Px(50.0)),
+                margin: Rect::all(Val::Px(10.)),
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
                 ..Default::default()
@@ -71,13 +245,12 @@ fn setup_menu(
             material: button_materials.normal.clone(),
             ..Default::default()
         })
-        .insert(PlayButton)
-        .insert(Menu)
+        .insert(marker)
         .with_children(|parent| {
             parent.spawn_bundle(TextBundle {
                 text: Text {
                     sections: vec![TextSection {
-                        value: "Play".to_string(),
+                        value: label.to_string(),
                         style: TextStyle {
                             font: font_assets.fira_sans.clone(),
                             font_size: 40.0,
@@ -89,39 +262,508 @@ fn setup_menu(
                 ..Default::default()
             });
         });
+}
 
-    let mut menu_transform = Transform::from_translation(Vec3::new(50., -150., 10.));
-    menu_transform.scale = Vec3::new(0.4, 0.4, 0.4);
+fn click_continue_button(
+    button_materials: Res<ButtonMaterials>,
+    mut state: ResMut<State<GameState>>,
+    mut continued_run: ResMut<ContinuedRun>,
+    mut interaction_query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>),
+        (Changed<Interaction>, With<ContinueButton>),
+    >,
+) {
+    for (interaction, mut material) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                if let Some(run) = SavedRun::load() {
+                    continued_run.0 = Some(run);
+                    state.set(GameState::Playing).unwrap();
+                }
+            }
+            Interaction::Hovered => {
+                *material = button_materials.hovered.clone();
+            }
+            Interaction::None => {
+                *material = button_materials.normal.clone();
+            }
+        }
+    }
+}
+
+fn click_play_button(
+    button_materials: Res<ButtonMaterials>,
+    mut state: ResMut<State<GameState>>,
+    mut interaction_query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>),
+        (Changed<Interaction>, With<PlayButton>),
+    >,
+) {
+    for (interaction, mut material) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                state.set(GameState::Playing).unwrap();
+            }
+            Interaction::Hovered => {
+                *material = button_materials.hovered.clone();
+            }
+            Interaction::None => {
+                *material = button_materials.normal.clone();
+            }
+        }
+    }
+}
+
+fn remove_menu(mut commands: Commands, menu_query: Query<Entity, With<Menu>>) {
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn click_difficulty_button(
+    button_materials: Res<ButtonMaterials>,
+    mut settings: ResMut<Settings>,
+    mut interaction_query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>, &Children),
+        (Changed<Interaction>, With<DifficultyButton>),
+    >,
+    mut texts: Query<&mut Text>,
+) {
+    for (interaction, mut material, children) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                settings.difficulty = settings.difficulty.next();
+                settings.save();
+                for child in children.iter() {
+                    if let Ok(mut text) = texts.get_mut(*child) {
+                        text.sections[0].value = format!("{:?}", settings.difficulty);
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *material = button_materials.hovered.clone();
+            }
+            Interaction::None => {
+                *material = button_materials.normal.clone();
+            }
+        }
+    }
+}
+
+fn click_journal_button(
+    button_materials: Res<ButtonMaterials>,
+    mut state: ResMut<State<GameState>>,
+    mut interaction_query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>),
+        (Changed<Interaction>, With<JournalButton>),
+    >,
+) {
+    for (interaction, mut material) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                state.set(GameState::Journal).unwrap();
+            }
+            Interaction::Hovered => {
+                *material = button_materials.hovered.clone();
+            }
+            Interaction::None => {
+                *material = button_materials.normal.clone();
+            }
+        }
+    }
+}
+
+fn setup_journal(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_materials: Res<ButtonMaterials>,
+    journal: Res<Journal>,
+    levels: Res<Levels>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mut forms: Vec<EntityForm> = (0..levels.win_level())
+        .map(|level| levels.level(level).form.clone())
+        .collect();
+    forms.push(EntityForm::Enemy);
+    let text_style = TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size: 30.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
     commands
-        .spawn_bundle(SpriteBundle {
-            material: materials.add(texture_assets.menu.clone().into()),
-            transform: menu_transform,
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(JournalScreen);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0., 0., 0., 0.6).into()),
             ..Default::default()
         })
-        .insert(Menu);
+        .insert(JournalScreen)
+        .with_children(|parent| {
+            for form in forms {
+                let line = match journal.entry(&form) {
+                    Some(_) if form == EntityForm::Enemy => format!("{:?}: met", form),
+                    Some(entry) => format!("{:?}: befriended {}", form, entry.befriended),
+                    None => "???".to_owned(),
+                };
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(5.)),
+                        ..Default::default()
+                    },
+                    text: Text {
+                        sections: vec![TextSection {
+                            value: line,
+                            style: text_style.clone(),
+                        }],
+                        alignment: Default::default(),
+                    },
+                    ..Default::default()
+                });
+            }
+            spawn_button(parent, &font_assets, &button_materials, "Back", BackButton);
+        });
+}
+
+fn click_back_button(
+    button_materials: Res<ButtonMaterials>,
+    mut state: ResMut<State<GameState>>,
+    mut interaction_query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>),
+        (Changed<Interaction>, With<BackButton>),
+    >,
+) {
+    for (interaction, mut material) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                state.set(GameState::Menu).unwrap();
+            }
+            Interaction::Hovered => {
+                *material = button_materials.hovered.clone();
+            }
+            Interaction::None => {
+                *material = button_materials.normal.clone();
+            }
+        }
+    }
 }
 
-type ButtonInteraction<'a> = (
-    Entity,
-    &'a Interaction,
-    &'a mut Handle<ColorMaterial>,
-    &'a Children,
-);
+fn remove_journal(mut commands: Commands, journal_query: Query<Entity, With<JournalScreen>>) {
+    for entity in journal_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
 
-fn click_play_button(
+fn click_settings_button(
+    button_materials: Res<ButtonMaterials>,
+    mut state: ResMut<State<GameState>>,
+    mut interaction_query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>),
+        (Changed<Interaction>, With<SettingsButton>),
+    >,
+) {
+    for (interaction, mut material) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                state.set(GameState::Settings).unwrap();
+            }
+            Interaction::Hovered => {
+                *material = button_materials.hovered.clone();
+            }
+            Interaction::None => {
+                *material = button_materials.normal.clone();
+            }
+        }
+    }
+}
+
+fn fullscreen_label(settings: &Settings) -> String {
+    if settings.fullscreen {
+        "Fullscreen: on".to_owned()
+    } else {
+        "Fullscreen: off".to_owned()
+    }
+}
+
+fn key_binding_label(settings: &Settings, rebinding: &Rebinding, control: GameControl) -> String {
+    if rebinding.0 == Some(control) {
+        format!("{:?}: ...", control)
+    } else {
+        match settings.bindings.key(control) {
+            Some(key) => format!("{:?}: {:?}", control, key),
+            None => format!("{:?}: -", control),
+        }
+    }
+}
+
+fn segment_material(
+    settings: &Settings,
+    button_materials: &ButtonMaterials,
+    segment: &VolumeSegment,
+) -> Handle<ColorMaterial> {
+    if segment.step <= settings.volume_step(segment.channel) {
+        button_materials.selected.clone()
+    } else {
+        button_materials.normal.clone()
+    }
+}
+
+fn setup_settings(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_materials: Res<ButtonMaterials>,
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let text_style = TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size: 30.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    let row_material = materials.add(Color::NONE.into());
+    commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(SettingsScreen);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0., 0., 0., 0.6).into()),
+            ..Default::default()
+        })
+        .insert(SettingsScreen)
+        .with_children(|parent| {
+            for (label, channel) in [
+                ("Music", VolumeChannel::Background),
+                ("Effects", VolumeChannel::Effects),
+            ]
+            .iter()
+            {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            margin: Rect::all(Val::Px(10.)),
+                            ..Default::default()
+                        },
+                        material: row_material.clone(),
+                        ..Default::default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(120.0), Val::Auto),
+                                ..Default::default()
+                            },
+                            text: Text::with_section(
+                                label.to_string(),
+                                text_style.clone(),
+                                Default::default(),
+                            ),
+                            ..Default::default()
+                        });
+                        for step in 1..=VOLUME_STEPS {
+                            let segment = VolumeSegment {
+                                channel: *channel,
+                                step,
+                            };
+                            parent
+                                .spawn_bundle(ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(20.0), Val::Px(30.0)),
+                                        margin: Rect::all(Val::Px(2.)),
+                                        ..Default::default()
+                                    },
+                                    material: segment_material(
+                                        &settings,
+                                        &button_materials,
+                                        &segment,
+                                    ),
+                                    ..Default::default()
+                                })
+                                .insert(segment);
+                        }
+                    });
+            }
+            spawn_button(
+                parent,
+                &font_assets,
+                &button_materials,
+                &fullscreen_label(&settings),
+                FullscreenButton,
+            );
+            for control in [
+                GameControl::Up,
+                GameControl::Down,
+                GameControl::Left,
+                GameControl::Right,
+            ]
+            .iter()
+            {
+                spawn_button(
+                    parent,
+                    &font_assets,
+                    &button_materials,
+                    &key_binding_label(&settings, &rebinding, *control),
+                    KeyBindingButton(*control),
+                );
+            }
+            spawn_button(parent, &font_assets, &button_materials, "Back", BackButton);
+        });
+}
+
+fn click_volume_segment(
+    mut settings: ResMut<Settings>,
+    interaction_query: Query<(&Interaction, &VolumeSegment), Changed<Interaction>>,
+) {
+    for (interaction, segment) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        // clicking the loudest selected segment again turns the volume down by one step
+        let step = if settings.volume_step(segment.channel) == segment.step {
+            segment.step - 1
+        } else {
+            segment.step
+        };
+        *settings.volume_mut(segment.channel) = step as f32 / VOLUME_STEPS as f32;
+    }
+}
+
+fn click_fullscreen_button(
+    button_materials: Res<ButtonMaterials>,
+    mut settings: ResMut<Settings>,
+    mut interaction_query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>),
+        (Changed<Interaction>, With<FullscreenButton>),
+    >,
+) {
+    for (interaction, mut material) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                settings.fullscreen = !settings.fullscreen;
+            }
+            Interaction::Hovered => {
+                *material = button_materials.hovered.clone();
+            }
+            Interaction::None => {
+                *material = button_materials.normal.clone();
+            }
+        }
+    }
+}
+
+fn click_key_binding_button(
+    button_materials: Res<ButtonMaterials>,
+    mut rebinding: ResMut<Rebinding>,
+    mut interaction_query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>, &KeyBindingButton),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, mut material, button) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                rebinding.0 = Some(button.0);
+            }
+            Interaction::Hovered => {
+                *material = button_materials.hovered.clone();
+            }
+            Interaction::None => {
+                *material = button_materials.normal.clone();
+            }
+        }
+    }
+}
+
+fn capture_key_binding(
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    let control = if let Some(control) = rebinding.0 {
+        control
+    } else {
+        return;
+    };
+    if let Some(key) = keyboard_input.get_just_pressed().next() {
+        // Escape keeps the old key
+        if *key != KeyCode::Escape {
+            settings.bindings.bind_key(control, *key);
+        }
+        rebinding.0 = None;
+    }
+}
+
+fn update_settings_screen(
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    button_materials: Res<ButtonMaterials>,
+    mut segments: Query<(&VolumeSegment, &mut Handle<ColorMaterial>)>,
+    fullscreen_buttons: Query<&Children, With<FullscreenButton>>,
+    key_binding_buttons: Query<(&KeyBindingButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !settings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (segment, mut material) in segments.iter_mut() {
+        *material = segment_material(&settings, &button_materials, segment);
+    }
+    for children in fullscreen_buttons.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = fullscreen_label(&settings);
+            }
+        }
+    }
+    for (button, children) in key_binding_buttons.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = key_binding_label(&settings, &rebinding, button.0);
+            }
+        }
+    }
+}
+
+fn remove_settings(
     mut commands: Commands,
+    settings: Res<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+    settings_query: Query<Entity, With<SettingsScreen>>,
+) {
+    settings.save();
+    rebinding.0 = None;
+    for entity in settings_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn click_results_button(
     button_materials: Res<ButtonMaterials>,
     mut state: ResMut<State<GameState>>,
-    mut interaction_query: Query<ButtonInteraction, (Changed<Interaction>, With<Button>)>,
-    text_query: Query<Entity, With<Text>>,
+    mut interaction_query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>),
+        (Changed<Interaction>, With<ResultsButton>),
+    >,
 ) {
-    for (button, interaction, mut material, children) in interaction_query.iter_mut() {
-        let text = text_query.get(children[0]).unwrap();
+    for (interaction, mut material) in interaction_query.iter_mut() {
         match *interaction {
             Interaction::Clicked => {
-                commands.entity(button).despawn();
-                commands.entity(text).despawn();
-                state.set(GameState::Playing).unwrap();
+                state.set(GameState::Results).unwrap();
             }
             Interaction::Hovered => {
                 *material = button_materials.hovered.clone();
@@ -133,8 +775,108 @@ fn click_play_button(
     }
 }
 
-fn remove_menu(mut commands: Commands, menu_query: Query<Entity, With<Menu>>) {
-    for entity in menu_query.iter() {
+fn format_time(seconds: f32) -> String {
+    let seconds = seconds.round() as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn describe_run(run: &RunStats) -> String {
+    let outcome = if run.won {
+        format!("Won in {}", format_time(run.seconds))
+    } else {
+        format!(
+            "Level {} for {}",
+            run.peak_level + 1,
+            format_time(run.seconds)
+        )
+    };
+    format!(
+        "{:?}: {}, {} friends, {} nopes",
+        run.difficulty,
+        outcome,
+        run.total_friends(),
+        run.nopes
+    )
+}
+
+fn describe_friends(run: &RunStats) -> String {
+    if run.friends.is_empty() {
+        return "No friends made".to_owned();
+    }
+    let friends: Vec<String> = run
+        .friends
+        .iter()
+        .map(|(form, count)| format!("{:?} {}", form, count))
+        .collect();
+    format!("Friends: {}", friends.join(", "))
+}
+
+fn setup_results(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_materials: Res<ButtonMaterials>,
+    history: Res<RunHistory>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mut lines = vec![];
+    if let Some(run) = history.last() {
+        lines.push("Last run".to_owned());
+        lines.push(describe_run(run));
+        lines.push(describe_friends(run));
+        lines.push(String::new());
+    }
+    lines.push("Best runs".to_owned());
+    let best = history.best(LEADERBOARD_SIZE);
+    if best.is_empty() {
+        lines.push("No finished runs yet".to_owned());
+    }
+    for (rank, run) in best.iter().enumerate() {
+        lines.push(format!("{}. {}", rank + 1, describe_run(run)));
+    }
+    let text_style = TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size: 24.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(ResultsScreen);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0., 0., 0., 0.6).into()),
+            ..Default::default()
+        })
+        .insert(ResultsScreen)
+        .with_children(|parent| {
+            for line in lines {
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(4.)),
+                        ..Default::default()
+                    },
+                    text: Text {
+                        sections: vec![TextSection {
+                            value: line,
+                            style: text_style.clone(),
+                        }],
+                        alignment: Default::default(),
+                    },
+                    ..Default::default()
+                });
+            }
+            spawn_button(parent, &font_assets, &button_materials, "Back", BackButton);
+        });
+}
+
+fn remove_results(mut commands: Commands, results_query: Query<Entity, With<ResultsScreen>>) {
+    for entity in results_query.iter() {
         commands.entity(entity).despawn_recursive();
     }
 }
@@ -0,0 +1,147 @@
+use crate::actions::{Actions, ActionsSystemLabels};
+use crate::loading::FontAssets;
+use crate::menu::{spawn_button, ButtonMaterials};
+use crate::GameState;
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::window::WindowFocused;
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(pause_game.system().after(ActionsSystemLabels::Input)),
+        )
+        .add_system_set(
+            SystemSet::on_enter(GameState::Paused).with_system(setup_pause_overlay.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Paused)
+                .with_system(
+                    click_pause_buttons
+                        .system()
+                        .label(PauseSystemLabels::Buttons),
+                )
+                .with_system(
+                    resume_with_pause_action
+                        .system()
+                        .after(ActionsSystemLabels::Input)
+                        .after(PauseSystemLabels::Buttons),
+                ),
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Paused).with_system(remove_pause_overlay.system()),
+        );
+    }
+}
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+enum PauseSystemLabels {
+    Buttons,
+}
+
+struct PauseOverlay;
+
+#[derive(Clone, Copy)]
+enum PauseButton {
+    Resume,
+    Restart,
+    Quit,
+}
+
+// The state stage loops within a frame, so the action is consumed to not resume right away
+fn pause_game(
+    mut state: ResMut<State<GameState>>,
+    mut actions: ResMut<Actions>,
+    mut focus_events: EventReader<WindowFocused>,
+) {
+    let lost_focus = focus_events.iter().any(|event| !event.focused);
+    if actions.pause || lost_focus {
+        actions.pause = false;
+        if let Err(error) = state.push(GameState::Paused) {
+            warn!("Failed to pause: {:?}", error);
+        }
+    }
+}
+
+fn setup_pause_overlay(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_materials: Res<ButtonMaterials>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0., 0., 0., 0.6).into()),
+            ..Default::default()
+        })
+        .insert(PauseOverlay)
+        .with_children(|parent| {
+            let buttons = [
+                ("Resume", PauseButton::Resume),
+                ("Restart", PauseButton::Restart),
+                ("Quit", PauseButton::Quit),
+            ];
+            for (label, button) in buttons.iter() {
+                spawn_button(parent, &font_assets, &button_materials, label, *button);
+            }
+        });
+}
+
+fn resume_with_pause_action(mut state: ResMut<State<GameState>>, mut actions: ResMut<Actions>) {
+    if actions.pause {
+        actions.pause = false;
+        if let Err(error) = state.pop() {
+            warn!("Failed to resume: {:?}", error);
+        }
+    }
+}
+
+// Resuming goes through the pause action, so a click and a key press in one frame pop only once
+fn click_pause_buttons(
+    button_materials: Res<ButtonMaterials>,
+    mut state: ResMut<State<GameState>>,
+    mut actions: ResMut<Actions>,
+    mut app_exit: EventWriter<AppExit>,
+    mut interaction_query: Query<
+        (&Interaction, &PauseButton, &mut Handle<ColorMaterial>),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut material) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => match button {
+                PauseButton::Resume => actions.pause = true,
+                // replacing unwinds the whole stack, so the run is cleaned up on the way
+                PauseButton::Restart => {
+                    if let Err(error) = state.replace(GameState::Restart) {
+                        warn!("Failed to restart: {:?}", error);
+                    }
+                }
+                PauseButton::Quit => app_exit.send(AppExit),
+            },
+            Interaction::Hovered => {
+                *material = button_materials.hovered.clone();
+            }
+            Interaction::None => {
+                *material = button_materials.normal.clone();
+            }
+        }
+    }
+}
+
+fn remove_pause_overlay(mut commands: Commands, overlay_query: Query<Entity, With<PauseOverlay>>) {
+    for entity in overlay_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -1,64 +1,168 @@
-use crate::actions::Actions;
-use crate::entities::{BefriendedEntity, GameEntity};
+use crate::actions::{Actions, ActionsSystemLabels};
+use crate::clock::GameClock;
+use crate::collision::{BumpEvent, Collider};
+use crate::courage::{CourageEvent, Encounter};
+use crate::difficulty::Difficulty;
+use crate::entities::{spawn_game_entity, BefriendedEntity, EntityForm, GameEntity};
+use crate::levels::Levels;
+use crate::savegame::ContinuedRun;
+use crate::settings::Settings;
+use crate::spatial::SpatialIndex;
+use crate::tuning::Tuning;
+use crate::visibility::{VisibilityPolygon, VisibilitySystemLabels};
 use crate::{GameState, GameWorld};
 use bevy::ecs::component::{ComponentDescriptor, StorageType};
 use bevy::prelude::*;
-use bevy_prototype_lyon::entity::ShapeColors;
+use bevy::utils::HashSet;
+use bevy_prototype_lyon::entity::{Path, ShapeBundle, ShapeColors};
 use bevy_prototype_lyon::prelude::{FillOptions, Geometry, GeometryBuilder, PathBuilder};
 use bevy_prototype_lyon::shapes;
 use bevy_prototype_lyon::utils::DrawMode;
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 
 pub struct PlayerPlugin;
 
 pub struct Player;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PlayerState {
     pub level: usize,
     pub courage: f32,
+    pub nearby_friends: usize,
     pub dead: bool,
+    pub won: bool,
+    pub difficulty: Difficulty,
 }
 
-impl Default for PlayerState {
-    fn default() -> Self {
+impl PlayerState {
+    pub fn new(courage: f32) -> Self {
         Self {
             dead: false,
+            won: false,
             level: 0,
-            courage: 50.0,
+            courage,
+            nearby_friends: 0,
+            difficulty: Difficulty::default(),
         }
     }
 }
 
 pub struct PlayerCamera;
 
+#[derive(Clone)]
 pub struct FieldOfView {
     half_angle: f32,
     height: f32,
+    size: f32,
+}
+
+const FIELD_OF_VIEW_DEFAULT_HEIGHT: f32 = 150.;
+const FIELD_OF_VIEW_MIN_HEIGHT: f32 = 80.;
+const FIELD_OF_VIEW_MAX_HEIGHT: f32 = 400.;
+const FIELD_OF_VIEW_DEFAULT_HALF_ANGLE: f32 = PI / 10.;
+const FIELD_OF_VIEW_ZOOM_SPEED: f32 = 25.;
+
+impl FieldOfView {
+    // The arc length of the cone stays constant, so looking further makes the view narrower
+    pub fn with_height(height: f32) -> Self {
+        FieldOfView::sized(height, 1.)
+    }
+
+    // A larger size scales the whole cone, zooming keeps the size
+    pub fn sized(height: f32, size: f32) -> Self {
+        let height = height.clamp(
+            FIELD_OF_VIEW_MIN_HEIGHT * size,
+            FIELD_OF_VIEW_MAX_HEIGHT * size,
+        );
+        FieldOfView {
+            half_angle: FIELD_OF_VIEW_DEFAULT_HALF_ANGLE * FIELD_OF_VIEW_DEFAULT_HEIGHT * size
+                / height,
+            height,
+            size,
+        }
+    }
+
+    pub fn with_size(size: f32) -> Self {
+        FieldOfView::sized(FIELD_OF_VIEW_DEFAULT_HEIGHT * size, size)
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    // The farthest a cone of the given size can reach when zoomed out
+    pub fn max_height(size: f32) -> f32 {
+        FIELD_OF_VIEW_MAX_HEIGHT * size
+    }
+
+    pub fn half_angle(&self) -> f32 {
+        self.half_angle
+    }
+
+    pub fn sees(&self, fov_transform: &Transform, point: Vec3) -> bool {
+        let player_rotation = fov_transform
+            .rotation
+            .angle_between(Quat::from_rotation_z(0.));
+        let player_rotation_side = fov_transform
+            .rotation
+            .angle_between(Quat::from_rotation_z(PI / 2.));
+        let fov_direction = Vec3::new(
+            if player_rotation_side > PI / 2. {
+                player_rotation.sin()
+            } else {
+                -player_rotation.sin()
+            },
+            player_rotation.cos(),
+            0.,
+        );
+        let point_from_player = point - fov_transform.translation;
+        point_from_player.length() < self.height
+            && point_from_player.angle_between(fov_direction).abs() < self.half_angle
+    }
+}
+
+impl Default for FieldOfView {
+    fn default() -> Self {
+        FieldOfView::with_height(FIELD_OF_VIEW_DEFAULT_HEIGHT)
+    }
 }
 pub struct InFieldOfView;
 
 pub struct BefriendEvent;
+// Sent for every befriended entity, unlike `BefriendEvent` which is quiet right before a level up
+pub struct FriendEvent {
+    pub form: EntityForm,
+}
+pub struct MeetEvent {
+    pub entity: Entity,
+    pub form: EntityForm,
+}
 pub struct DyingEvent;
 pub struct LevelUpEvent;
 pub struct NopeEvent;
+// Sent for every nope, unlike `NopeEvent` which is quiet when the nope empties the courage
+pub struct RejectEvent;
 pub struct WonEvent;
 
 #[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
-enum PlayerSystemLabels {
+pub enum PlayerSystemLabels {
     MovePlayer,
     MoveFieldOfView,
+    ZoomFieldOfView,
+    SettleCourage,
 }
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.insert_resource(CursorPosition {
-            position: Vec2::new(0., 0.),
-        })
-        .register_component(ComponentDescriptor::new::<InFieldOfView>(
+        app.register_component(ComponentDescriptor::new::<InFieldOfView>(
             StorageType::SparseSet,
         ))
         .add_event::<BefriendEvent>()
+        .add_event::<FriendEvent>()
+        .add_event::<MeetEvent>()
         .add_event::<NopeEvent>()
+        .add_event::<RejectEvent>()
         .add_event::<DyingEvent>()
         .add_event::<WonEvent>()
         .add_event::<LevelUpEvent>()
@@ -69,7 +173,12 @@ impl Plugin for PlayerPlugin {
         )
         .add_system_set(
             SystemSet::on_update(GameState::Playing)
-                .with_system(move_player.system().label(PlayerSystemLabels::MovePlayer))
+                .with_system(
+                    move_player
+                        .system()
+                        .label(PlayerSystemLabels::MovePlayer)
+                        .after(ActionsSystemLabels::Replay),
+                )
                 .with_system(
                     move_field_of_view
                         .system()
@@ -77,21 +186,51 @@ impl Plugin for PlayerPlugin {
                         .after(PlayerSystemLabels::MovePlayer),
                 )
                 .with_system(
-                    mark_entities_in_field_of_view
+                    zoom_field_of_view
                         .system()
+                        .label(PlayerSystemLabels::ZoomFieldOfView)
                         .after(PlayerSystemLabels::MoveFieldOfView),
                 )
+                .with_system(
+                    redraw_field_of_view
+                        .system()
+                        .after(VisibilitySystemLabels::Compute),
+                )
+                .with_system(
+                    mark_entities_in_field_of_view
+                        .system()
+                        .before(PlayerSystemLabels::SettleCourage)
+                        .after(VisibilitySystemLabels::Compute),
+                )
+                .with_system(
+                    bump_courage
+                        .system()
+                        .before(PlayerSystemLabels::SettleCourage),
+                )
+                .with_system(
+                    settle_courage
+                        .system()
+                        .label(PlayerSystemLabels::SettleCourage),
+                )
                 .with_system(remove_fov_on_death.system()),
         )
         .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(remove_player.system()));
     }
 }
 
-struct CursorPosition {
-    position: Vec2,
-}
-
-fn spawn_player(mut commands: Commands) {
+fn spawn_player(
+    mut commands: Commands,
+    levels: Res<Levels>,
+    tuning: Res<Tuning>,
+    settings: Res<Settings>,
+    continued_run: Res<ContinuedRun>,
+) {
+    let (position, facing) = continued_run
+        .0
+        .as_ref()
+        .map_or((Vec2::ZERO, Vec2::new(0., 1.)), |run| {
+            (run.player_position(), run.player_facing())
+        });
     let shape = shapes::RegularPolygon {
         sides: 3,
         feature: shapes::RegularPolygonFeature::Radius(30.0),
@@ -105,56 +244,113 @@ fn spawn_player(mut commands: Commands) {
                 outline: Color::ANTIQUE_WHITE,
             },
             DrawMode::Fill(FillOptions::default()),
-            Transform::from_translation(Vec3::new(0., 0., 20.)),
+            Transform {
+                translation: position.extend(20.),
+                rotation: Quat::from_rotation_z(-facing.angle_between(Vec2::new(0., 1.))),
+                ..Default::default()
+            },
         ))
+        .insert(Collider::player())
         .insert(Player);
-    commands.insert_resource(PlayerState::default());
+    commands.insert_resource(continued_run.0.as_ref().map_or_else(
+        || {
+            let difficulty = tuning.difficulty(settings.difficulty);
+            let mut player_state =
+                PlayerState::new(levels.level(0).starting_courage * difficulty.starting_courage);
+            player_state.difficulty = settings.difficulty;
+            player_state
+        },
+        |run| run.player_state(),
+    ));
 }
 
-pub fn build_fov_geometry(field_of_view: &FieldOfView) -> impl Geometry {
+fn build_fov_geometry(outline: &[Vec2]) -> impl Geometry {
     let mut builder = PathBuilder::new();
-    let radius = field_of_view.height * field_of_view.half_angle.sin();
-    let left = Vec2::new(
-        -radius,
-        field_of_view.height * field_of_view.half_angle.cos(),
-    );
     builder.move_to(Vec2::ZERO);
-    builder.line_to(left);
-    builder.arc(
-        Vec2::new(0., 0.),
-        Vec2::new(field_of_view.height, field_of_view.height),
-        -field_of_view.half_angle * 2.,
-        -PI,
-    );
+    for point in outline {
+        builder.line_to(*point);
+    }
     builder.line_to(Vec2::ZERO);
     builder.build()
 }
 
-fn spawn_field_of_view(mut commands: Commands) {
-    let field_of_view = FieldOfView {
-        half_angle: PI / 10.,
-        height: 150.,
-    };
+fn build_fov_bundle(outline: &[Vec2], transform: Transform) -> ShapeBundle {
+    GeometryBuilder::build_as(
+        &build_fov_geometry(outline),
+        ShapeColors {
+            main: Color::AQUAMARINE,
+            outline: Color::ANTIQUE_WHITE,
+        },
+        DrawMode::Fill(FillOptions::default()),
+        transform,
+    )
+}
+
+fn spawn_field_of_view(
+    mut commands: Commands,
+    tuning: Res<Tuning>,
+    settings: Res<Settings>,
+    continued_run: Res<ContinuedRun>,
+) {
+    let (field_of_view, position) = continued_run.0.as_ref().map_or_else(
+        || {
+            let size = tuning.difficulty(settings.difficulty).field_of_view;
+            (FieldOfView::with_size(size), Vec2::ZERO)
+        },
+        |run| (run.field_of_view(&tuning), run.player_position()),
+    );
     commands
-        .spawn_bundle(GeometryBuilder::build_as(
-            &build_fov_geometry(&field_of_view),
-            ShapeColors {
-                main: Color::AQUAMARINE,
-                outline: Color::ANTIQUE_WHITE,
-            },
-            DrawMode::Fill(FillOptions::default()),
-            Transform::from_translation(Vec3::new(0., 0., 5.)),
+        .spawn_bundle(build_fov_bundle(
+            VisibilityPolygon::unoccluded(&field_of_view).outline(),
+            Transform::from_translation(position.extend(5.)),
         ))
         .insert(field_of_view);
 }
 
+fn zoom_field_of_view(
+    actions: Res<Actions>,
+    mut field_of_view_query: Query<&mut FieldOfView>,
+    player_state: Res<PlayerState>,
+) {
+    if player_state.dead || player_state.won {
+        return;
+    }
+    let zoom = if let Some(zoom) = actions.field_of_view_zoom {
+        zoom
+    } else {
+        return;
+    };
+    for mut field_of_view in field_of_view_query.iter_mut() {
+        *field_of_view = FieldOfView::sized(
+            field_of_view.height + zoom * FIELD_OF_VIEW_ZOOM_SPEED,
+            field_of_view.size,
+        );
+    }
+}
+
+// The cone is rebuilt every frame, clipped to what the player can actually see.
+// Removing the path first makes the new one count as added, which gets it tessellated again.
+fn redraw_field_of_view(
+    mut commands: Commands,
+    visibility: Res<VisibilityPolygon>,
+    field_of_view_query: Query<Entity, With<FieldOfView>>,
+    player_state: Res<PlayerState>,
+) {
+    if player_state.dead || player_state.won {
+        return;
+    }
+    for entity in field_of_view_query.iter() {
+        // only the path is taken over, the entity keeps its own transform
+        let bundle = build_fov_bundle(visibility.outline(), Transform::default());
+        commands.entity(entity).remove::<Path>().insert(bundle.path);
+    }
+}
+
 fn move_player(
-    time: Res<Time>,
+    clock: Res<GameClock>,
     actions: Res<Actions>,
     world: Res<GameWorld>,
-    windows: Res<Windows>,
-    mut cursor_moved: EventReader<CursorMoved>,
-    mut cursor_position: ResMut<CursorPosition>,
+    tuning: Res<Tuning>,
     mut player_query: Query<&mut Transform, (With<Player>, Without<PlayerCamera>)>,
     mut player_camera_query: Query<&mut Transform, (With<PlayerCamera>, Without<Player>)>,
     player_state: Res<PlayerState>,
@@ -165,24 +361,19 @@ fn move_player(
         }
         return;
     }
-    if player_state.level > 5 {
+    if player_state.won {
         return;
     }
-    let speed = 200.;
+    let speed = tuning.player_speed;
     let movement = if let Some(player_movement) = actions.player_movement {
         Vec3::new(
-            player_movement.x * speed * time.delta_seconds(),
-            player_movement.y * speed * time.delta_seconds(),
+            player_movement.x * speed * clock.delta_seconds(),
+            player_movement.y * speed * clock.delta_seconds(),
             0.,
         )
     } else {
         Vec3::ZERO
     };
-    if let Some(new_cursor_position) = cursor_moved.iter().last() {
-        let window = windows.get(new_cursor_position.id).unwrap();
-        let size = Vec2::new(window.width() as f32, window.height() as f32);
-        cursor_position.position = new_cursor_position.position - size / 2.0;
-    };
     for mut player_transform in player_query.iter_mut() {
         player_transform.translation += movement;
         player_transform.translation.x = player_transform
@@ -193,8 +384,10 @@ fn move_player(
             .translation
             .y
             .clamp(-world.border, world.border);
-        player_transform.rotation =
-            Quat::from_rotation_z(-cursor_position.position.angle_between(Vec2::new(0., 1.)));
+        if actions.aim_direction.length() > f32::EPSILON {
+            player_transform.rotation =
+                Quat::from_rotation_z(-actions.aim_direction.angle_between(Vec2::new(0., 1.)));
+        }
         for mut player_camera_transform in player_camera_query.iter_mut() {
             player_camera_transform.translation = player_transform.translation;
         }
@@ -206,7 +399,7 @@ fn move_field_of_view(
     mut field_of_view_query: Query<&mut Transform, (With<FieldOfView>, Without<Player>)>,
     player_state: Res<PlayerState>,
 ) {
-    if player_state.dead || player_state.level > 5 {
+    if player_state.dead || player_state.won {
         return;
     }
     for player_transform in player_query.iter() {
@@ -226,102 +419,152 @@ fn mark_entities_in_field_of_view(
     field_of_view: Query<(&Transform, &FieldOfView), Without<GameEntity>>,
     mut entities: Query<(Entity, &Transform, &mut GameEntity), Without<BefriendedEntity>>,
     mut befriend_event: EventWriter<BefriendEvent>,
+    mut friend_event: EventWriter<FriendEvent>,
+    mut meet_event: EventWriter<MeetEvent>,
     mut nope_event: EventWriter<NopeEvent>,
-    mut level_up_event: EventWriter<LevelUpEvent>,
-    mut won_event: EventWriter<WonEvent>,
-    mut die_event: EventWriter<DyingEvent>,
+    mut reject_event: EventWriter<RejectEvent>,
     mut player_state: ResMut<PlayerState>,
-    time: Res<Time>,
+    spatial_index: Res<SpatialIndex>,
+    visibility: Res<VisibilityPolygon>,
+    levels: Res<Levels>,
+    tuning: Res<Tuning>,
+    clock: Res<GameClock>,
 ) {
-    if player_state.dead || player_state.level > 5 {
+    if player_state.dead || player_state.won {
         return;
     }
+    let mut seen = HashSet::default();
     if let Ok((fov_transform, field_of_view)) = field_of_view.single() {
-        let player_rotation = fov_transform
-            .rotation
-            .angle_between(Quat::from_rotation_z(0.));
-        let player_rotation_side = fov_transform
-            .rotation
-            .angle_between(Quat::from_rotation_z(PI / 2.));
-        let fov_direction = Vec3::new(
-            if player_rotation_side > PI / 2. {
-                player_rotation.sin()
-            } else {
-                -player_rotation.sin()
-            },
-            player_rotation.cos(),
-            0.,
-        );
-        let millis_since_startup = time.time_since_startup().as_millis();
-        for (entity, transform, mut game_entity) in entities.iter_mut() {
-            let entity_from_player = transform.translation - fov_transform.translation;
-            if (entity_from_player.length() < field_of_view.height)
-                && (entity_from_player.angle_between(fov_direction).abs()
-                    < field_of_view.half_angle)
+        let candidates =
+            spatial_index.query_circle(fov_transform.translation.truncate(), field_of_view.height);
+        for candidate in candidates {
+            let (entity, transform, mut game_entity) = match entities.get_mut(candidate) {
+                Ok(entity) => entity,
+                Err(_) => continue,
+            };
+            if field_of_view.sees(fov_transform, transform.translation)
+                && visibility.is_visible(entity, transform.translation.truncate())
             {
-                let level_diff: i32 =
-                    (player_state.level as i32) - (game_entity.true_form.level() as i32);
-                if level_diff >= 0 {
+                if !game_entity.known {
+                    game_entity.known = true;
+                    meet_event.send(MeetEvent {
+                        entity,
+                        form: game_entity.true_form.clone(),
+                    });
+                }
+                // enemies back off from a courageous look, see the enemies module
+                if game_entity.true_form == EntityForm::Enemy
+                    && player_state.courage >= tuning.enemies.repel_courage
+                {
+                    continue;
+                }
+                let form_level = levels
+                    .form_level(&game_entity.true_form)
+                    .filter(|form_level| *form_level <= player_state.level);
+                seen.insert(entity);
+                if let Some(form_level) = form_level {
+                    game_entity.acquaintance +=
+                        clock.delta_seconds() / tuning.getting_to_know.exposure_time(form_level);
+                    if game_entity.acquaintance < 1. {
+                        continue;
+                    }
                     let new_game_entity = GameEntity {
                         true_form: game_entity.true_form.clone(),
                         current_direction: game_entity.current_direction.clone(),
                         last_contact: game_entity.last_contact,
+                        // bumps before befriending do not count for the friend
+                        last_bump: None,
                         next_direction_change: game_entity.next_direction_change,
+                        spawned_at: game_entity.spawned_at,
                         known: true,
+                        acquaintance: 1.,
                     };
-                    commands.entity(entity).despawn();
-                    commands
-                        .spawn_bundle(GeometryBuilder::build_as(
-                            &new_game_entity.true_form.to_shape(),
-                            ShapeColors {
-                                main: Color::LIME_GREEN,
-                                outline: Color::ANTIQUE_WHITE,
-                            },
-                            DrawMode::Fill(FillOptions::default()),
-                            transform.clone(),
-                        ))
-                        .insert(new_game_entity)
-                        .insert(BefriendedEntity);
-                    if level_diff == 0 {
-                        player_state.courage += 20.;
-                    } else {
-                        player_state.courage += 20. / (level_diff as f32);
-                    }
-                    if player_state.courage < 99.6 {
+                    commands.entity(entity).despawn_recursive();
+                    spawn_game_entity(
+                        &mut commands,
+                        new_game_entity,
+                        transform.clone(),
+                        true,
+                        true,
+                    );
+                    friend_event.send(FriendEvent {
+                        form: game_entity.true_form.clone(),
+                    });
+                    if let Some(CourageEvent::Befriend) =
+                        player_state.encounter(Encounter::Befriend { form_level }, &levels, &tuning)
+                    {
                         befriend_event.send(BefriendEvent);
                     }
-                } else if millis_since_startup - game_entity.last_contact.as_millis() > 2000 {
-                    player_state.courage -= 20.;
-                    if player_state.courage > 0.1 {
+                } else if clock
+                    .elapsed()
+                    .checked_sub(game_entity.last_contact)
+                    .unwrap_or_default()
+                    > tuning.nope_cooldown()
+                {
+                    reject_event.send(RejectEvent);
+                    if let Some(CourageEvent::Nope) =
+                        player_state.encounter(Encounter::Nope, &levels, &tuning)
+                    {
                         nope_event.send(NopeEvent);
                     }
-                    game_entity.last_contact = time.time_since_startup();
+                    game_entity.last_contact = clock.elapsed();
                 }
             }
         }
-        player_state.courage = player_state.courage.clamp(0., 100.);
-        if player_state.courage > 99.5 {
-            player_state.courage = 25.;
-            player_state.level += 1;
-            if player_state.level > 5 {
-                player_state.courage = 100.;
-                won_event.send(WonEvent);
-            } else {
-                level_up_event.send(LevelUpEvent);
-            }
-        } else if player_state.courage < 0.1 {
-            player_state.dead = true;
-            die_event.send(DyingEvent);
+    }
+    // getting to know an entity slowly fades while it is out of view
+    for (entity, _, mut game_entity) in entities.iter_mut() {
+        if game_entity.acquaintance > 0. && !seen.contains(&entity) {
+            game_entity.acquaintance = (game_entity.acquaintance
+                - tuning.getting_to_know.decay_per_second * clock.delta_seconds())
+            .max(0.);
+        }
+    }
+}
+
+fn settle_courage(
+    mut player_state: ResMut<PlayerState>,
+    levels: Res<Levels>,
+    tuning: Res<Tuning>,
+    mut level_up_event: EventWriter<LevelUpEvent>,
+    mut won_event: EventWriter<WonEvent>,
+    mut die_event: EventWriter<DyingEvent>,
+) {
+    match player_state.settle(&levels, &tuning) {
+        Some(CourageEvent::LevelUp) => level_up_event.send(LevelUpEvent),
+        Some(CourageEvent::Won) => won_event.send(WonEvent),
+        Some(CourageEvent::Dying) => die_event.send(DyingEvent),
+        _ => (),
+    }
+}
+
+fn bump_courage(
+    mut bump_events: EventReader<BumpEvent>,
+    mut nope_event: EventWriter<NopeEvent>,
+    mut reject_event: EventWriter<RejectEvent>,
+    mut player_state: ResMut<PlayerState>,
+    levels: Res<Levels>,
+    tuning: Res<Tuning>,
+) {
+    for bump in bump_events.iter() {
+        let courage = tuning.bump_courage.for_kind(bump.kind);
+        if courage < 0. && !player_state.is_over() {
+            reject_event.send(RejectEvent);
+        }
+        if let Some(CourageEvent::Nope) =
+            player_state.encounter(Encounter::Bump { courage }, &levels, &tuning)
+        {
+            nope_event.send(NopeEvent);
         }
     }
 }
 
 fn remove_fov_on_death(
     mut commands: Commands,
-    mut events: EventReader<DyingEvent>,
+    player_state: Res<PlayerState>,
     fov_query: Query<Entity, With<FieldOfView>>,
 ) {
-    if let Some(_event) = events.iter().last() {
+    if player_state.dead {
         for fov in fov_query.iter() {
             commands.entity(fov).despawn();
         }
@@ -0,0 +1,209 @@
+use crate::actions::{Actions, ActionsSystemLabels};
+use crate::clock::{ClockSystemLabels, GameClock};
+use crate::difficulty::Difficulty;
+use crate::player::{DyingEvent, WonEvent};
+use crate::rng::GameRng;
+use crate::savegame::ContinuedRun;
+use crate::settings::Settings;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const RECORD_VARIABLE: &str = "NOBODY_RECORD";
+const REPLAY_VARIABLE: &str = "NOBODY_REPLAY";
+
+// Records or replays runs as set in the environment, or always plays live without `from_env`
+pub struct ReplayPlugin {
+    pub from_env: bool,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let mode = if self.from_env {
+            ReplayMode::from_env()
+        } else {
+            ReplayMode::Live
+        };
+        if let ReplayMode::Replaying { recording, .. } = &mode {
+            app.insert_resource(GameRng::from_seed(recording.seed));
+        }
+        app.insert_resource(mode)
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                replay_clock.system().after(ClockSystemLabels::Tick),
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, replay_difficulty.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing).with_system(start_session.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(
+                        replay_input
+                            .system()
+                            .label(ActionsSystemLabels::Replay)
+                            .after(ActionsSystemLabels::Input),
+                    )
+                    .with_system(record_input.system().after(ActionsSystemLabels::Input))
+                    .with_system(save_recording_on_end.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Playing).with_system(save_recording.system()),
+            );
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Recording {
+    seed: u64,
+    // Scales spawning, courage and the field of view, so it has to match for the same run
+    difficulty: Difficulty,
+    frames: Vec<RecordedFrame>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordedFrame {
+    player_movement: Option<(f32, f32)>,
+    field_of_view_zoom: Option<f32>,
+    aim_direction: (f32, f32),
+    // Seconds of game time in this frame
+    delta: f32,
+}
+
+pub enum ReplayMode {
+    Live,
+    Recording { path: PathBuf, recording: Recording },
+    Replaying { recording: Recording, frame: usize },
+}
+
+impl ReplayMode {
+    fn from_env() -> Self {
+        if let Ok(path) = std::env::var(REPLAY_VARIABLE) {
+            match load_recording(Path::new(&path)) {
+                Ok(recording) => {
+                    info!("Replaying {} frames from {}", recording.frames.len(), path);
+                    return ReplayMode::Replaying {
+                        recording,
+                        frame: 0,
+                    };
+                }
+                Err(error) => error!("Failed to load replay from {}: {}", path, error),
+            }
+        }
+        if let Ok(path) = std::env::var(RECORD_VARIABLE) {
+            info!("Recording input to {}", path);
+            return ReplayMode::Recording {
+                path: PathBuf::from(path),
+                recording: Recording::default(),
+            };
+        }
+        ReplayMode::Live
+    }
+}
+
+fn load_recording(path: &Path) -> Result<Recording, String> {
+    let content = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    ron::from_str(&content).map_err(|error| error.to_string())
+}
+
+fn start_session(
+    mut mode: ResMut<ReplayMode>,
+    rng: Res<GameRng>,
+    settings: Res<Settings>,
+    continued_run: Res<ContinuedRun>,
+) {
+    match &mut *mode {
+        ReplayMode::Recording { recording, .. } => {
+            if continued_run.0.is_some() {
+                warn!("Recording a continued run, replaying it will start a fresh run instead");
+            }
+            *recording = Recording {
+                seed: rng.seed(),
+                difficulty: settings.difficulty,
+                frames: vec![],
+            };
+        }
+        ReplayMode::Replaying { frame, .. } => *frame = 0,
+        ReplayMode::Live => (),
+    }
+}
+
+fn record_input(mut mode: ResMut<ReplayMode>, actions: Res<Actions>, clock: Res<GameClock>) {
+    if let ReplayMode::Recording { recording, .. } = &mut *mode {
+        recording.frames.push(RecordedFrame {
+            player_movement: actions
+                .player_movement
+                .map(|movement| (movement.x, movement.y)),
+            field_of_view_zoom: actions.field_of_view_zoom,
+            aim_direction: (actions.aim_direction.x, actions.aim_direction.y),
+            delta: clock.delta_seconds(),
+        });
+    }
+}
+
+fn replay_input(mut mode: ResMut<ReplayMode>, mut actions: ResMut<Actions>) {
+    if let ReplayMode::Replaying { recording, frame } = &mut *mode {
+        if let Some(recorded) = recording.frames.get(*frame) {
+            actions.player_movement = recorded.player_movement.map(|(x, y)| Vec2::new(x, y));
+            actions.field_of_view_zoom = recorded.field_of_view_zoom;
+            actions.aim_direction = Vec2::new(recorded.aim_direction.0, recorded.aim_direction.1);
+            *frame += 1;
+            if *frame == recording.frames.len() {
+                info!("Replay finished");
+            }
+        } else {
+            actions.player_movement = None;
+            actions.field_of_view_zoom = None;
+        }
+    }
+}
+
+fn replay_clock(mode: Res<ReplayMode>, state: Res<State<GameState>>, mut clock: ResMut<GameClock>) {
+    if state.current() != &GameState::Playing {
+        return;
+    }
+    if let ReplayMode::Replaying { recording, frame } = &*mode {
+        if let Some(recorded) = recording.frames.get(*frame) {
+            clock.set_delta(Duration::from_secs_f32(recorded.delta));
+        }
+    }
+}
+
+// Runs before any run starts, so the player state and the spawner use the recorded difficulty
+fn replay_difficulty(mode: Res<ReplayMode>, mut settings: ResMut<Settings>) {
+    if let ReplayMode::Replaying { recording, .. } = &*mode {
+        if settings.difficulty != recording.difficulty {
+            info!("Replaying on {:?}", recording.difficulty);
+            settings.difficulty = recording.difficulty;
+        }
+    }
+}
+
+fn save_recording_on_end(
+    mode: Res<ReplayMode>,
+    mut dying_events: EventReader<DyingEvent>,
+    mut won_events: EventReader<WonEvent>,
+) {
+    if dying_events.iter().last().is_some() || won_events.iter().last().is_some() {
+        write_recording(&mode);
+    }
+}
+
+fn save_recording(mode: Res<ReplayMode>) {
+    write_recording(&mode);
+}
+
+fn write_recording(mode: &ReplayMode) {
+    if let ReplayMode::Recording { path, recording } = mode {
+        let result = ron::to_string(recording)
+            .map_err(|error| error.to_string())
+            .and_then(|content| fs::write(path, content).map_err(|error| error.to_string()));
+        match result {
+            Ok(()) => info!("Saved {} frames to {:?}", recording.frames.len(), path),
+            Err(error) => error!("Failed to save recording to {:?}: {}", path, error),
+        }
+    }
+}
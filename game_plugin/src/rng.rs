@@ -0,0 +1,136 @@
+use crate::GameState;
+use bevy::prelude::*;
+use rand::{random, Error, RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+const SEED_VARIABLE: &str = "NOBODY_SEED";
+// Derives the audio seed from the gameplay seed without both producing the same stream
+const AUDIO_SEED_SALT: u64 = 0x5eed_a0d1_05ee_d5a1;
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GameRng>()
+            .init_resource::<AudioRng>()
+            .add_system_set(
+                SystemSet::on_exit(GameState::Playing).with_system(reseed_rng.system()),
+            );
+    }
+}
+
+// All gameplay randomness is drawn from here, so a run can be reproduced from its seed
+pub struct GameRng {
+    seed: u64,
+    fixed_seed: bool,
+    // the algorithm behind `StdRng`, used directly since it can report its position in the stream
+    rng: ChaCha12Rng,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        GameRng {
+            seed,
+            fixed_seed: true,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // How far into the stream of its seed the generator is, so a saved run can continue from there
+    pub fn word_position(&self) -> u64 {
+        self.rng.get_word_pos() as u64
+    }
+
+    pub fn resume(&mut self, seed: u64, word_position: u64) {
+        self.seed = seed;
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+        self.rng.set_word_pos(word_position as u128);
+    }
+
+    fn restart(&mut self) {
+        if !self.fixed_seed {
+            self.seed = random();
+        }
+        self.rng = ChaCha12Rng::seed_from_u64(self.seed);
+        info!("Using seed {}", self.seed);
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        let seed = std::env::var(SEED_VARIABLE).ok().and_then(|seed| {
+            seed.parse::<u64>()
+                .map_err(|error| warn!("Ignoring invalid {}: {}", SEED_VARIABLE, error))
+                .ok()
+        });
+        let mut rng = GameRng {
+            fixed_seed: seed.is_some(),
+            ..GameRng::from_seed(seed.unwrap_or_default())
+        };
+        rng.restart();
+        rng
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+// Cosmetic choices like which sound to play, kept apart so they do not shift the gameplay stream
+pub struct AudioRng(ChaCha12Rng);
+
+impl AudioRng {
+    fn for_seed(seed: u64) -> Self {
+        AudioRng(ChaCha12Rng::seed_from_u64(seed ^ AUDIO_SEED_SALT))
+    }
+}
+
+impl FromWorld for AudioRng {
+    fn from_world(world: &mut World) -> Self {
+        let seed = world
+            .get_resource::<GameRng>()
+            .map_or_else(random, |rng| rng.seed());
+        AudioRng::for_seed(seed)
+    }
+}
+
+impl RngCore for AudioRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+fn reseed_rng(mut rng: ResMut<GameRng>, mut audio_rng: ResMut<AudioRng>) {
+    rng.restart();
+    *audio_rng = AudioRng::for_seed(rng.seed());
+}
@@ -0,0 +1,312 @@
+use crate::actions::Actions;
+use crate::behaviour::Behaviour;
+use crate::clock::GameClock;
+use crate::enemies::Enemy;
+use crate::entities::{spawn_game_entity, BefriendedEntity, EntityForm, EntityTimer, GameEntity};
+use crate::levels::Levels;
+use crate::player::{DyingEvent, FieldOfView, Player, PlayerState, WonEvent};
+use crate::rng::GameRng;
+use crate::stats::RunStats;
+use crate::storage;
+use crate::tuning::Tuning;
+use crate::GameState;
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use bevy::window::WindowCloseRequested;
+use serde::{Deserialize, Serialize};
+
+const SAVE_FILE: &str = "run.ron";
+
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ContinuedRun>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing).with_system(restore_run.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(forget_continued_run.system())
+                    .with_system(remove_save_on_end.system()),
+            )
+            .add_event::<SaveRunEvent>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Paused).with_system(request_save.system()),
+            )
+            // closing the window exits without pausing, so exiting saves the run as well
+            .add_system_to_stage(CoreStage::PostUpdate, save_run.system());
+    }
+}
+
+// A run in progress. Times are stored relative to the moment of saving,
+// since the game clock keeps running over all runs of a session.
+#[derive(Serialize, Deserialize)]
+pub struct SavedRun {
+    player_state: PlayerState,
+    player_position: (f32, f32),
+    player_facing: (f32, f32),
+    rng_seed: u64,
+    rng_word_position: u64,
+    field_of_view_height: f32,
+    spawn_timer_elapsed: f32,
+    entities: Vec<SavedEntity>,
+    stats: RunStats,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEntity {
+    form: EntityForm,
+    position: (f32, f32),
+    direction: (f32, f32),
+    since_contact: f32,
+    since_bump: Option<f32>,
+    until_direction_change: f32,
+    age: f32,
+    known: bool,
+    acquaintance: f32,
+    befriended: bool,
+    patrol_center: Option<(f32, f32)>,
+    enemy: Option<SavedEnemy>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEnemy {
+    chasing: bool,
+    repelled_for: f32,
+}
+
+impl SavedRun {
+    pub fn exists() -> bool {
+        storage::data_file(SAVE_FILE).map_or(false, |path| path.exists())
+    }
+
+    pub fn load() -> Option<Self> {
+        storage::data_file(SAVE_FILE).and_then(|path| storage::load(&path))
+    }
+
+    fn save(&self) {
+        if let Some(path) = storage::data_file(SAVE_FILE) {
+            storage::save(&path, self);
+        }
+    }
+
+    fn remove() {
+        if let Some(path) = storage::data_file(SAVE_FILE) {
+            storage::remove(&path);
+        }
+    }
+
+    pub fn player_state(&self) -> PlayerState {
+        self.player_state.clone()
+    }
+
+    pub fn player_position(&self) -> Vec2 {
+        Vec2::new(self.player_position.0, self.player_position.1)
+    }
+
+    pub fn player_facing(&self) -> Vec2 {
+        Vec2::new(self.player_facing.0, self.player_facing.1)
+    }
+
+    pub fn stats(&self) -> RunStats {
+        self.stats.clone()
+    }
+
+    pub fn field_of_view(&self, tuning: &Tuning) -> FieldOfView {
+        FieldOfView::sized(
+            self.field_of_view_height,
+            tuning
+                .difficulty(self.player_state.difficulty)
+                .field_of_view,
+        )
+    }
+}
+
+impl SavedEntity {
+    fn to_game_entity(&self, now: Duration) -> GameEntity {
+        GameEntity {
+            true_form: self.form.clone(),
+            current_direction: Vec2::new(self.direction.0, self.direction.1),
+            last_contact: seconds_before(now, self.since_contact),
+            last_bump: self
+                .since_bump
+                .map(|since_bump| seconds_before(now, since_bump)),
+            next_direction_change: now + Duration::from_secs_f32(self.until_direction_change),
+            spawned_at: seconds_before(now, self.age),
+            known: self.known,
+            acquaintance: self.acquaintance,
+        }
+    }
+}
+
+fn seconds_before(now: Duration, seconds: f32) -> Duration {
+    now.checked_sub(Duration::from_secs_f32(seconds))
+        .unwrap_or_default()
+}
+
+fn seconds_between(earlier: Duration, later: Duration) -> f32 {
+    later.checked_sub(earlier).unwrap_or_default().as_secs_f32()
+}
+
+// The run picked with "Continue" in the menu, used instead of a fresh start when entering `Playing`
+#[derive(Default)]
+pub struct ContinuedRun(pub Option<SavedRun>);
+
+fn restore_run(
+    mut commands: Commands,
+    continued_run: Res<ContinuedRun>,
+    levels: Res<Levels>,
+    clock: Res<GameClock>,
+    mut timer: ResMut<EntityTimer>,
+    mut rng: ResMut<GameRng>,
+    mut actions: ResMut<Actions>,
+) {
+    // a fresh start abandons any saved run, so it cannot be continued later on
+    let run = if let Some(run) = &continued_run.0 {
+        run
+    } else {
+        SavedRun::remove();
+        return;
+    };
+    let now = clock.elapsed();
+    rng.resume(run.rng_seed, run.rng_word_position);
+    // the player turns towards the aim direction every frame
+    actions.aim_direction = run.player_facing();
+    timer.reset();
+    timer.tick(Duration::from_secs_f32(run.spawn_timer_elapsed));
+    for saved in run.entities.iter() {
+        let transform =
+            Transform::from_translation(Vec3::new(saved.position.0, saved.position.1, 10.));
+        let entity = spawn_game_entity(
+            &mut commands,
+            saved.to_game_entity(now),
+            transform,
+            levels.is_known(&saved.form, run.player_state.level),
+            saved.befriended,
+        );
+        if let Some((x, y)) = saved.patrol_center {
+            commands.entity(entity).insert(Behaviour::Patrol {
+                center: Vec2::new(x, y),
+            });
+        }
+        if let Some(enemy) = &saved.enemy {
+            commands.entity(entity).insert(Enemy {
+                chasing: enemy.chasing,
+                repelled_until: now + Duration::from_secs_f32(enemy.repelled_for),
+            });
+        }
+    }
+    info!("Continuing a run with {} entities", run.entities.len());
+}
+
+fn forget_continued_run(mut continued_run: ResMut<ContinuedRun>) {
+    if continued_run.0.is_some() {
+        continued_run.0 = None;
+    }
+}
+
+struct SaveRunEvent;
+
+fn request_save(mut save_events: EventWriter<SaveRunEvent>) {
+    save_events.send(SaveRunEvent);
+}
+
+fn save_run(
+    state: Res<State<GameState>>,
+    mut save_events: EventReader<SaveRunEvent>,
+    mut exit_events: EventReader<AppExit>,
+    mut close_events: EventReader<WindowCloseRequested>,
+    rng: Res<GameRng>,
+    // only inserted once the first run starts
+    player_state: Option<Res<PlayerState>>,
+    stats: Res<RunStats>,
+    clock: Res<GameClock>,
+    timer: Res<EntityTimer>,
+    player_query: Query<&Transform, With<Player>>,
+    field_of_view_query: Query<&FieldOfView>,
+    entities: Query<(
+        &Transform,
+        &GameEntity,
+        Option<&BefriendedEntity>,
+        Option<&Behaviour>,
+        Option<&Enemy>,
+    )>,
+) {
+    let requested = save_events.iter().last().is_some();
+    let exiting = exit_events.iter().last().is_some() || close_events.iter().last().is_some();
+    let in_run = matches!(state.current(), GameState::Playing | GameState::Paused);
+    if !(requested || (exiting && in_run)) {
+        return;
+    }
+    let player_state = match player_state {
+        Some(player_state) if !player_state.is_over() => player_state,
+        _ => return,
+    };
+    let player_transform = if let Ok(transform) = player_query.single() {
+        transform
+    } else {
+        return;
+    };
+    let now = clock.elapsed();
+    let run = SavedRun {
+        player_state: player_state.clone(),
+        player_position: (
+            player_transform.translation.x,
+            player_transform.translation.y,
+        ),
+        player_facing: {
+            let facing = player_transform.rotation * Vec3::Y;
+            (facing.x, facing.y)
+        },
+        rng_seed: rng.seed(),
+        rng_word_position: rng.word_position(),
+        field_of_view_height: field_of_view_query
+            .single()
+            .map(|field_of_view| field_of_view.height())
+            .unwrap_or_else(|_| FieldOfView::default().height()),
+        spawn_timer_elapsed: timer.elapsed().as_secs_f32(),
+        entities: entities
+            .iter()
+            .map(
+                |(transform, game_entity, befriended, behaviour, enemy)| SavedEntity {
+                    form: game_entity.true_form.clone(),
+                    position: (transform.translation.x, transform.translation.y),
+                    direction: (
+                        game_entity.current_direction.x,
+                        game_entity.current_direction.y,
+                    ),
+                    since_contact: seconds_between(game_entity.last_contact, now),
+                    since_bump: game_entity
+                        .last_bump
+                        .map(|last_bump| seconds_between(last_bump, now)),
+                    until_direction_change: seconds_between(now, game_entity.next_direction_change),
+                    age: seconds_between(game_entity.spawned_at, now),
+                    known: game_entity.known,
+                    acquaintance: game_entity.acquaintance,
+                    befriended: befriended.is_some(),
+                    patrol_center: match behaviour {
+                        Some(Behaviour::Patrol { center }) => Some((center.x, center.y)),
+                        _ => None,
+                    },
+                    enemy: enemy.map(|enemy| SavedEnemy {
+                        chasing: enemy.chasing,
+                        repelled_for: seconds_between(now, enemy.repelled_until),
+                    }),
+                },
+            )
+            .collect(),
+        stats: stats.clone(),
+    };
+    run.save();
+}
+
+fn remove_save_on_end(
+    mut dying_events: EventReader<DyingEvent>,
+    mut won_events: EventReader<WonEvent>,
+) {
+    if dying_events.iter().last().is_some() || won_events.iter().last().is_some() {
+        SavedRun::remove();
+    }
+}
@@ -0,0 +1,93 @@
+use crate::actions::Bindings;
+use crate::difficulty::Difficulty;
+use crate::storage;
+use bevy::prelude::*;
+use bevy::window::WindowMode;
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_FILE: &str = "settings.ron";
+pub const VOLUME_STEPS: usize = 10;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Settings::load())
+            .add_system(apply_window_mode.system());
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub background_volume: f32,
+    pub effects_volume: f32,
+    pub fullscreen: bool,
+    pub bindings: Bindings,
+    // Used for new runs, a continued run keeps its own
+    pub difficulty: Difficulty,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            background_volume: 0.2,
+            effects_volume: 0.3,
+            fullscreen: false,
+            bindings: Bindings::default(),
+            difficulty: Difficulty::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum VolumeChannel {
+    Background,
+    Effects,
+}
+
+impl Settings {
+    fn load() -> Self {
+        storage::config_file(SETTINGS_FILE)
+            .and_then(|path| storage::load(&path))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = storage::config_file(SETTINGS_FILE) {
+            storage::save(&path, self);
+        }
+    }
+
+    pub fn volume(&self, channel: VolumeChannel) -> f32 {
+        match channel {
+            VolumeChannel::Background => self.background_volume,
+            VolumeChannel::Effects => self.effects_volume,
+        }
+    }
+
+    pub fn volume_mut(&mut self, channel: VolumeChannel) -> &mut f32 {
+        match channel {
+            VolumeChannel::Background => &mut self.background_volume,
+            VolumeChannel::Effects => &mut self.effects_volume,
+        }
+    }
+
+    // The volume as one of the steps shown in the settings screen
+    pub fn volume_step(&self, channel: VolumeChannel) -> usize {
+        (self.volume(channel) * VOLUME_STEPS as f32).round() as usize
+    }
+}
+
+fn apply_window_mode(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_mode(if settings.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        });
+    }
+}
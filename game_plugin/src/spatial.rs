@@ -0,0 +1,98 @@
+use crate::entities::{EntitiesSystemLabels, GameEntity};
+use crate::{GameState, GameWorld};
+use bevy::prelude::*;
+
+const CELL_SIZE: f32 = 100.;
+
+pub struct SpatialPlugin;
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub enum SpatialSystemLabels {
+    UpdateIndex,
+}
+
+impl Plugin for SpatialPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SpatialIndex>().add_system_set(
+            SystemSet::on_update(GameState::Playing).with_system(
+                update_spatial_index
+                    .system()
+                    .label(SpatialSystemLabels::UpdateIndex)
+                    .after(EntitiesSystemLabels::MoveEntities),
+            ),
+        );
+    }
+}
+
+// Uniform grid over the game world holding all game entities by position
+pub struct SpatialIndex {
+    half_extent: f32,
+    columns: usize,
+    cells: Vec<Vec<Entity>>,
+}
+
+impl FromWorld for SpatialIndex {
+    fn from_world(world: &mut World) -> Self {
+        let border = world.get_resource::<GameWorld>().unwrap().border;
+        SpatialIndex::new(border)
+    }
+}
+
+impl SpatialIndex {
+    pub fn new(half_extent: f32) -> Self {
+        let columns = ((2. * half_extent) / CELL_SIZE).ceil().max(1.) as usize;
+        SpatialIndex {
+            half_extent,
+            columns,
+            cells: vec![vec![]; columns * columns],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
+        }
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2) {
+        let (column, row) = self.cell(position);
+        self.cells[row * self.columns + column].push(entity);
+    }
+
+    // All entities in cells overlapping the given rectangle. Callers still need an exact test.
+    pub fn query_rect(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = Entity> + '_ {
+        let (min_column, min_row) = self.cell(min);
+        let (max_column, max_row) = self.cell(max);
+        (min_row..=max_row).flat_map(move |row| {
+            (min_column..=max_column)
+                .flat_map(move |column| self.cells[row * self.columns + column].iter().copied())
+        })
+    }
+
+    pub fn query_circle(&self, center: Vec2, radius: f32) -> impl Iterator<Item = Entity> + '_ {
+        self.query_rect(
+            center - Vec2::new(radius, radius),
+            center + Vec2::new(radius, radius),
+        )
+    }
+
+    fn cell(&self, position: Vec2) -> (usize, usize) {
+        let to_cell = |coordinate: f32| {
+            (((coordinate + self.half_extent) / CELL_SIZE)
+                .floor()
+                .max(0.) as usize)
+                .min(self.columns - 1)
+        };
+        (to_cell(position.x), to_cell(position.y))
+    }
+}
+
+fn update_spatial_index(
+    mut spatial_index: ResMut<SpatialIndex>,
+    entities: Query<(Entity, &Transform), With<GameEntity>>,
+) {
+    spatial_index.clear();
+    for (entity, transform) in entities.iter() {
+        spatial_index.insert(entity, transform.translation.truncate());
+    }
+}
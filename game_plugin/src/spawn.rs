@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+const SPAWN_ATTEMPTS: usize = 10;
+// Minimal distance between a new entity and the tip of the field of view
+pub const FIELD_OF_VIEW_MARGIN: f32 = 100.;
+
+#[derive(Deserialize, Clone)]
+pub struct SpawnRegion {
+    pub name: String,
+    min: (f32, f32),
+    max: (f32, f32),
+}
+
+impl SpawnRegion {
+    pub fn is_valid(&self) -> bool {
+        self.min.0 <= self.max.0 && self.min.1 <= self.max.1
+    }
+
+    fn bounds(&self) -> (Vec2, Vec2) {
+        (
+            Vec2::new(self.min.0, self.min.1),
+            Vec2::new(self.max.0, self.max.1),
+        )
+    }
+}
+
+// Picks a random position inside one of the regions (or the whole world if there are none)
+// that is at least `keep_away` but at most `max_distance` from the player.
+// Falls back to the candidate closest to that ring.
+pub fn pick_spawn_position<R: Rng + ?Sized>(
+    rng: &mut R,
+    regions: &[&SpawnRegion],
+    border: f32,
+    player_position: Vec2,
+    keep_away: f32,
+    max_distance: f32,
+) -> Vec2 {
+    let world_min = Vec2::new(-border, -border);
+    let world_max = Vec2::new(border, border);
+    let mut best_candidate = Vec2::ZERO;
+    let mut best_miss = f32::INFINITY;
+    for _ in 0..SPAWN_ATTEMPTS {
+        let (min, max) = if regions.is_empty() {
+            (world_min, world_max)
+        } else {
+            regions[rng.gen_range(0..regions.len())].bounds()
+        };
+        let candidate = Vec2::new(rng.gen_range(min.x..=max.x), rng.gen_range(min.y..=max.y))
+            .max(world_min)
+            .min(world_max);
+        let distance = candidate.distance(player_position);
+        let miss = (keep_away - distance).max(distance - max_distance);
+        if miss <= 0. {
+            return candidate;
+        }
+        if miss < best_miss {
+            best_candidate = candidate;
+            best_miss = miss;
+        }
+    }
+    best_candidate
+}
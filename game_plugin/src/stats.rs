@@ -0,0 +1,209 @@
+use crate::clock::GameClock;
+use crate::difficulty::Difficulty;
+use crate::entities::EntityForm;
+use crate::player::{
+    DyingEvent, FriendEvent, PlayerState, PlayerSystemLabels, RejectEvent, WonEvent,
+};
+use crate::savegame::ContinuedRun;
+use crate::settings::Settings;
+use crate::storage;
+use crate::GameState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+const RUNS_FILE: &str = "runs.ron";
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<RunStats>()
+            .insert_resource(RunHistory::load())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing).with_system(start_run_stats.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing).with_system(
+                    track_run_stats
+                        .system()
+                        .after(PlayerSystemLabels::SettleCourage),
+                ),
+            );
+    }
+}
+
+// Statistics of a single run, kept in a saved run while it is in progress
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct RunStats {
+    pub seconds: f32,
+    pub friends: Vec<(EntityForm, u32)>,
+    pub nopes: u32,
+    pub peak_level: usize,
+    pub won: bool,
+    pub difficulty: Difficulty,
+}
+
+impl RunStats {
+    pub fn total_friends(&self) -> u32 {
+        self.friends.iter().map(|(_, count)| count).sum()
+    }
+
+    fn add_friend(&mut self, form: &EntityForm) {
+        if let Some((_, count)) = self.friends.iter_mut().find(|(known, _)| known == form) {
+            *count += 1;
+        } else {
+            self.friends.push((form.clone(), 1));
+        }
+    }
+
+    // Won runs are better the faster they were, others the further they got and the longer they lasted
+    fn compare(&self, other: &RunStats) -> Ordering {
+        other
+            .won
+            .cmp(&self.won)
+            .then_with(|| {
+                if self.won {
+                    self.seconds
+                        .partial_cmp(&other.seconds)
+                        .unwrap_or(Ordering::Equal)
+                } else {
+                    other.peak_level.cmp(&self.peak_level).then_with(|| {
+                        other
+                            .seconds
+                            .partial_cmp(&self.seconds)
+                            .unwrap_or(Ordering::Equal)
+                    })
+                }
+            })
+            .then_with(|| other.total_friends().cmp(&self.total_friends()))
+            .then_with(|| self.nopes.cmp(&other.nopes))
+    }
+}
+
+// All finished runs, oldest first
+#[derive(Serialize, Deserialize, Default)]
+pub struct RunHistory {
+    runs: Vec<RunStats>,
+}
+
+impl RunHistory {
+    fn load() -> Self {
+        storage::data_file(RUNS_FILE)
+            .and_then(|path| storage::load(&path))
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        if let Some(path) = storage::data_file(RUNS_FILE) {
+            storage::save(&path, self);
+        }
+    }
+
+    pub fn last(&self) -> Option<&RunStats> {
+        self.runs.last()
+    }
+
+    pub fn best(&self, count: usize) -> Vec<&RunStats> {
+        let mut runs: Vec<&RunStats> = self.runs.iter().collect();
+        runs.sort_by(|first, second| first.compare(second));
+        runs.truncate(count);
+        runs
+    }
+}
+
+fn start_run_stats(
+    mut stats: ResMut<RunStats>,
+    settings: Res<Settings>,
+    continued_run: Res<ContinuedRun>,
+) {
+    *stats = continued_run.0.as_ref().map_or_else(
+        || RunStats {
+            difficulty: settings.difficulty,
+            ..RunStats::default()
+        },
+        |run| run.stats(),
+    );
+}
+
+fn track_run_stats(
+    mut stats: ResMut<RunStats>,
+    mut history: ResMut<RunHistory>,
+    player_state: Res<PlayerState>,
+    clock: Res<GameClock>,
+    mut friend_events: EventReader<FriendEvent>,
+    mut reject_events: EventReader<RejectEvent>,
+    mut dying_events: EventReader<DyingEvent>,
+    mut won_events: EventReader<WonEvent>,
+) {
+    for event in friend_events.iter() {
+        stats.add_friend(&event.form);
+    }
+    stats.nopes += reject_events.iter().count() as u32;
+    stats.peak_level = stats.peak_level.max(player_state.level);
+    let died = dying_events.iter().last().is_some();
+    let won = won_events.iter().last().is_some();
+    if died || won {
+        stats.won = won;
+        info!("Run finished after {:.1} seconds", stats.seconds);
+        history.runs.push(stats.clone());
+        history.save();
+    } else if !player_state.is_over() {
+        stats.seconds += clock.delta_seconds();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(won: bool, seconds: f32, peak_level: usize) -> RunStats {
+        RunStats {
+            seconds,
+            peak_level,
+            won,
+            ..RunStats::default()
+        }
+    }
+
+    #[test]
+    fn won_runs_rank_above_lost_runs() {
+        let won = run(true, 300., 5);
+        let lost = run(false, 30., 5);
+        assert_eq!(won.compare(&lost), Ordering::Less);
+        assert_eq!(lost.compare(&won), Ordering::Greater);
+    }
+
+    #[test]
+    fn faster_wins_rank_higher() {
+        let fast = run(true, 120., 5);
+        let slow = run(true, 240., 5);
+        assert_eq!(fast.compare(&slow), Ordering::Less);
+    }
+
+    #[test]
+    fn lost_runs_rank_by_peak_level_before_time() {
+        let further = run(false, 60., 3);
+        let longer = run(false, 600., 2);
+        assert_eq!(further.compare(&longer), Ordering::Less);
+        let same_level_longer = run(false, 90., 3);
+        assert_eq!(same_level_longer.compare(&further), Ordering::Less);
+    }
+
+    #[test]
+    fn best_runs_are_sorted_and_truncated() {
+        let history = RunHistory {
+            runs: vec![
+                run(false, 60., 1),
+                run(true, 240., 5),
+                run(false, 60., 4),
+                run(true, 120., 5),
+            ],
+        };
+        let best = history.best(3);
+        assert_eq!(best.len(), 3);
+        assert!(best[0].won && best[0].seconds == 120.);
+        assert!(best[1].won && best[1].seconds == 240.);
+        assert_eq!(best[2].peak_level, 4);
+    }
+}
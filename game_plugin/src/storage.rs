@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const GAME_DIRECTORY: &str = "nobody_is_perfect";
+
+// Progress kept between runs lives in the platform's data directory
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|directory| directory.join(GAME_DIRECTORY).join(name))
+}
+
+// Preferences like volumes and key bindings live in the platform's config directory
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|directory| directory.join(GAME_DIRECTORY).join(name))
+}
+
+pub fn load<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    if !path.exists() {
+        return None;
+    }
+    let result = fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|content| ron::from_str(&content).map_err(|error| error.to_string()));
+    match result {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!("Failed to load {:?}: {}", path, error);
+            None
+        }
+    }
+}
+
+pub fn save<T: Serialize>(path: &Path, value: &T) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .map_err(|error| error.to_string())
+        .and_then(|_| {
+            ron::ser::to_string_pretty(value, PrettyConfig::default())
+                .map_err(|error| error.to_string())
+        })
+        .and_then(|content| fs::write(path, content).map_err(|error| error.to_string()));
+    if let Err(error) = result {
+        error!("Failed to save {:?}: {}", path, error);
+    }
+}
+
+pub fn remove(path: &Path) {
+    if !path.exists() {
+        return;
+    }
+    if let Err(error) = fs::remove_file(path) {
+        error!("Failed to remove {:?}: {}", path, error);
+    }
+}
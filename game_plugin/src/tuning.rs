@@ -0,0 +1,276 @@
+use crate::difficulty::DifficultyPresets;
+use crate::entities::EntityKind;
+use crate::levels::Levels;
+use crate::loading::DataAssets;
+use crate::player::FieldOfView;
+use crate::spawn::FIELD_OF_VIEW_MARGIN;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::{BoxedFuture, Duration};
+use rand::Rng;
+use serde::Deserialize;
+
+pub struct TuningPlugin;
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<Tuning>()
+            .init_asset_loader::<TuningLoader>()
+            .add_system(reload_tuning.system());
+    }
+}
+
+#[derive(Deserialize, TypeUuid, Clone)]
+#[uuid = "492221ad-be2d-498d-bc4a-48c2fc6ab385"]
+pub struct Tuning {
+    pub player_speed: f32,
+    pub entity_speed: f32,
+    pub nope_cooldown: f32,
+    pub bump_cooldown: f32,
+    pub min_direction_change: f32,
+    pub max_direction_change: f32,
+    pub population: PopulationCaps,
+    pub despawn_distance: f32,
+    pub max_entity_age: f32,
+    pub steering: Steering,
+    pub formation: Formation,
+    pub enemies: Enemies,
+    pub getting_to_know: GettingToKnow,
+    pub bump_courage: BumpCourage,
+    pub difficulty: DifficultyPresets,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Steering {
+    pub flee_distance: f32,
+    pub hunt_distance: f32,
+    pub flock_distance: f32,
+    pub patrol_radius: f32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Formation {
+    pub spacing: f32,
+    pub max_speed_factor: f32,
+    pub friend_distance: f32,
+    pub courage_loss_reduction: f32,
+    pub min_courage_loss: f32,
+}
+
+impl Formation {
+    // Befriended entities close to the player soften any courage loss
+    pub fn courage_loss_factor(&self, nearby_friends: usize) -> f32 {
+        (1. - self.courage_loss_reduction * nearby_friends as f32).max(self.min_courage_loss)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Enemies {
+    pub drain_distance: f32,
+    pub drain_per_second: f32,
+    // Looking at an enemy with at least this much courage drives it away
+    pub repel_courage: f32,
+    pub repel_duration: f32,
+    pub repel_speed_factor: f32,
+}
+
+impl Enemies {
+    pub fn repel_duration(&self) -> Duration {
+        Duration::from_secs_f32(self.repel_duration)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct GettingToKnow {
+    pub base_time: f32,
+    pub time_per_level: f32,
+    // Fraction of the progress lost per second while out of view
+    pub decay_per_second: f32,
+}
+
+impl GettingToKnow {
+    // Seconds an entity has to stay in view before it is befriended
+    pub fn exposure_time(&self, form_level: usize) -> f32 {
+        self.base_time + self.time_per_level * form_level as f32
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct PopulationCaps {
+    pub enemies: usize,
+    pub unknown: usize,
+    pub befriended: usize,
+}
+
+impl PopulationCaps {
+    pub fn cap(&self, kind: EntityKind) -> usize {
+        match kind {
+            EntityKind::Enemy => self.enemies,
+            EntityKind::Unknown => self.unknown,
+            EntityKind::Befriended => self.befriended,
+        }
+    }
+}
+
+// Courage gained (or lost if negative) when bumping into an entity of the given kind
+#[derive(Deserialize, Clone)]
+pub struct BumpCourage {
+    pub enemy: f32,
+    pub unknown: f32,
+    pub befriended: f32,
+}
+
+impl BumpCourage {
+    pub fn for_kind(&self, kind: EntityKind) -> f32 {
+        match kind {
+            EntityKind::Enemy => self.enemy,
+            EntityKind::Unknown => self.unknown,
+            EntityKind::Befriended => self.befriended,
+        }
+    }
+}
+
+impl Tuning {
+    pub fn nope_cooldown(&self) -> Duration {
+        Duration::from_secs_f32(self.nope_cooldown)
+    }
+
+    pub fn bump_cooldown(&self) -> Duration {
+        Duration::from_secs_f32(self.bump_cooldown)
+    }
+
+    pub fn max_entity_age(&self) -> Duration {
+        Duration::from_secs_f32(self.max_entity_age)
+    }
+
+    pub fn direction_change_delay<R: Rng + ?Sized>(&self, rng: &mut R) -> Duration {
+        Duration::from_secs_f32(
+            self.min_direction_change
+                + (self.max_direction_change - self.min_direction_change) * rng.gen::<f32>(),
+        )
+    }
+
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.min_direction_change < 0. || self.max_direction_change < self.min_direction_change {
+            return Err(anyhow::anyhow!("Invalid direction change interval"));
+        }
+        if self.player_speed < 0.
+            || self.entity_speed < 0.
+            || self.formation.max_speed_factor < 0.
+            || self.enemies.repel_speed_factor < 0.
+        {
+            return Err(anyhow::anyhow!("Speeds cannot be negative"));
+        }
+        if self.getting_to_know.decay_per_second < 0. || self.enemies.drain_per_second < 0. {
+            return Err(anyhow::anyhow!("Decay and drain rates cannot be negative"));
+        }
+        if self.despawn_distance <= 0. {
+            return Err(anyhow::anyhow!("The despawn distance has to be positive"));
+        }
+        // without new unknown entities or room for friends, no run could be won
+        if self.population.unknown == 0 || self.population.befriended == 0 {
+            return Err(anyhow::anyhow!(
+                "Unknown and befriended entities need a population cap"
+            ));
+        }
+        if self.nope_cooldown < 0.
+            || self.bump_cooldown < 0.
+            || self.max_entity_age < 0.
+            || self.enemies.repel_duration < 0.
+        {
+            return Err(anyhow::anyhow!("Durations cannot be negative"));
+        }
+        if !self.difficulty.has_positive_factors() {
+            return Err(anyhow::anyhow!("Difficulty factors have to be positive"));
+        }
+        // entities spawn outside the field of view, but close enough to not be despawned right away
+        if self.difficulty.all().iter().any(|modifiers| {
+            FieldOfView::max_height(modifiers.field_of_view) + FIELD_OF_VIEW_MARGIN
+                >= self.despawn_distance
+        }) {
+            return Err(anyhow::anyhow!(
+                "The despawn distance has to be beyond the spawn distance"
+            ));
+        }
+        if self.steering.patrol_radius <= 0. {
+            return Err(anyhow::anyhow!("The patrol radius has to be positive"));
+        }
+        if self.getting_to_know.exposure_time(0) <= 0. {
+            return Err(anyhow::anyhow!(
+                "Getting to know an entity has to take some time"
+            ));
+        }
+        Ok(())
+    }
+
+    // Checks what depends on the levels, whenever the tuning or the levels change
+    pub fn validate_with(&self, levels: &Levels) -> Result<(), anyhow::Error> {
+        self.difficulty.validate(levels)?;
+        if (0..=levels.win_level()).any(|level| self.getting_to_know.exposure_time(level) <= 0.) {
+            return Err(anyhow::anyhow!(
+                "Getting to know an entity has to take some time on every level"
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        ron::from_str(include_str!("../../assets/data/balance.tuning"))
+            .expect("Failed to parse the bundled tuning values")
+    }
+}
+
+#[derive(Default)]
+pub struct TuningLoader;
+
+impl AssetLoader for TuningLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let tuning: Tuning = ron::de::from_bytes(bytes)?;
+            tuning.validate()?;
+            load_context.set_default_asset(LoadedAsset::new(tuning));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tuning"]
+    }
+}
+
+fn reload_tuning(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<Tuning>>,
+    tunings: Res<Assets<Tuning>>,
+    levels: Option<Res<Levels>>,
+    data_assets: Option<Res<DataAssets>>,
+) {
+    let data_assets = if let Some(data_assets) = data_assets {
+        data_assets
+    } else {
+        return;
+    };
+    for event in events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            if handle != &data_assets.tuning {
+                continue;
+            }
+            if let Some(tuning) = tunings.get(handle) {
+                if let Some(Err(error)) = levels.as_ref().map(|levels| tuning.validate_with(levels))
+                {
+                    error!("Ignoring reloaded tuning values: {}", error);
+                    continue;
+                }
+                info!("Reloaded tuning values");
+                commands.insert_resource(tuning.clone());
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
+use crate::levels::Levels;
 use crate::loading::FontAssets;
 use crate::player::{DyingEvent, LevelUpEvent, PlayerState, WonEvent};
 use crate::GameState;
+use bevy::ecs::component::Component;
 use bevy::prelude::*;
 
 pub struct UiPlugin;
@@ -15,7 +17,8 @@ impl Plugin for UiPlugin {
                     .with_system(spawn_retry_ui.system())
                     .with_system(spawn_won_text.system())
                     .with_system(update_courage_level.system())
-                    .with_system(click_retry_button.system()),
+                    .with_system(click_retry_button.system())
+                    .with_system(click_results_button.system()),
             )
             .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(remove_ui.system()));
     }
@@ -25,6 +28,7 @@ struct Ui;
 struct CourageMeter;
 struct CourageMeterRest;
 struct RetryButton;
+struct ResultsButton;
 struct CourageLevel;
 
 struct ButtonMaterials {
@@ -45,6 +49,7 @@ impl FromWorld for ButtonMaterials {
 fn spawn_ui(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
+    levels: Res<Levels>,
     mut color_materials: ResMut<Assets<ColorMaterial>>,
 ) {
     let background = color_materials.add(Color::GRAY.into());
@@ -111,7 +116,7 @@ fn spawn_ui(
                 .spawn_bundle(TextBundle {
                     text: Text {
                         sections: vec![TextSection {
-                            value: "Courage level: 1/7".to_string(),
+                            value: format!("Courage level: 1/{}", levels.win_level() + 1),
                             style: TextStyle {
                                 font_size: 30.0,
                                 color: Color::BLACK,
@@ -150,11 +155,15 @@ fn update_courage_level(
     mut level_up_events: EventReader<LevelUpEvent>,
     mut won_events: EventReader<WonEvent>,
     player_state: Res<PlayerState>,
+    levels: Res<Levels>,
 ) {
     if level_up_events.iter().last().is_some() || won_events.iter().last().is_some() {
         for mut text in courage_level.iter_mut() {
-            text.sections.first_mut().unwrap().value =
-                format!("Courage level: {}/7", player_state.level + 1)
+            text.sections.first_mut().unwrap().value = format!(
+                "Courage level: {}/{}",
+                player_state.level + 1,
+                levels.win_level() + 1
+            )
         }
     }
 }
@@ -167,43 +176,66 @@ fn spawn_retry_ui(
     mut won_events: EventReader<WonEvent>,
 ) {
     if dying_events.iter().last().is_some() || won_events.iter().last().is_some() {
-        commands
-            .spawn_bundle(ButtonBundle {
-                style: Style {
-                    size: Size::new(Val::Px(150.0), Val::Px(65.0)),
-                    margin: Rect {
-                        right: Val::Auto,
-                        left: Val::Auto,
-                        top: Val::Auto,
-                        bottom: Val::Percent(20.),
-                    },
-                    justify_content: JustifyContent::Center,
-                    align_items: AlignItems::Center,
-                    ..Default::default()
+        spawn_end_button(
+            &mut commands,
+            &font_assets,
+            &button_materials,
+            "Restart",
+            RetryButton,
+        );
+        spawn_end_button(
+            &mut commands,
+            &font_assets,
+            &button_materials,
+            "Results",
+            ResultsButton,
+        );
+    }
+}
+
+fn spawn_end_button<T: Component>(
+    commands: &mut Commands,
+    font_assets: &FontAssets,
+    button_materials: &ButtonMaterials,
+    label: &str,
+    marker: T,
+) {
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                margin: Rect {
+                    right: Val::Auto,
+                    left: Val::Auto,
+                    top: Val::Auto,
+                    bottom: Val::Percent(20.),
+                },
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: button_materials.normal.clone(),
+            ..Default::default()
+        })
+        .insert(marker)
+        .insert(Ui)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text {
+                    sections: vec![TextSection {
+                        value: label.to_string(),
+                        style: TextStyle {
+                            font_size: 40.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                            font: font_assets.fira_sans.clone(),
+                            ..Default::default()
+                        },
+                    }],
+                    alignment: Default::default(),
                 },
-                material: button_materials.normal.clone(),
                 ..Default::default()
-            })
-            .insert(RetryButton)
-            .insert(Ui)
-            .with_children(|parent| {
-                parent.spawn_bundle(TextBundle {
-                    text: Text {
-                        sections: vec![TextSection {
-                            value: "Restart".to_string(),
-                            style: TextStyle {
-                                font_size: 40.0,
-                                color: Color::rgb(0.9, 0.9, 0.9),
-                                font: font_assets.fira_sans.clone(),
-                                ..Default::default()
-                            },
-                        }],
-                        alignment: Default::default(),
-                    },
-                    ..Default::default()
-                });
             });
-    }
+        });
 }
 
 fn spawn_won_text(
@@ -261,7 +293,7 @@ fn click_retry_button(
     mut state: ResMut<State<GameState>>,
     mut interaction_query: Query<
         (&Interaction, &mut Handle<ColorMaterial>),
-        (Changed<Interaction>, With<Button>),
+        (Changed<Interaction>, With<RetryButton>),
     >,
 ) {
     for (interaction, mut material) in interaction_query.iter_mut() {
@@ -279,6 +311,29 @@ fn click_retry_button(
     }
 }
 
+fn click_results_button(
+    button_materials: Res<ButtonMaterials>,
+    mut state: ResMut<State<GameState>>,
+    mut interaction_query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>),
+        (Changed<Interaction>, With<ResultsButton>),
+    >,
+) {
+    for (interaction, mut material) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                state.set(GameState::Results).unwrap();
+            }
+            Interaction::Hovered => {
+                *material = button_materials.hovered.clone();
+            }
+            Interaction::None => {
+                *material = button_materials.normal.clone();
+            }
+        }
+    }
+}
+
 fn remove_ui(mut commands: Commands, text_query: Query<Entity, With<Ui>>) {
     for entity in text_query.iter() {
         commands.entity(entity).despawn_recursive();
@@ -0,0 +1,139 @@
+use crate::collision::ENTITY_RADIUS;
+use crate::entities::GameEntity;
+use crate::player::{FieldOfView, PlayerSystemLabels};
+use crate::spatial::{SpatialIndex, SpatialSystemLabels};
+use crate::tuning::Tuning;
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct VisibilityPlugin;
+
+#[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
+pub enum VisibilitySystemLabels {
+    Compute,
+}
+
+impl Plugin for VisibilityPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<VisibilityPolygon>().add_system_set(
+            SystemSet::on_update(GameState::Playing).with_system(
+                compute_visibility
+                    .system()
+                    .label(VisibilitySystemLabels::Compute)
+                    .after(PlayerSystemLabels::ZoomFieldOfView)
+                    .after(SpatialSystemLabels::UpdateIndex),
+            ),
+        );
+    }
+}
+
+const RAYS_PER_RADIAN: f32 = 60.;
+const MIN_RAYS: usize = 8;
+
+// The part of the field of view that is not hidden behind entities
+#[derive(Default)]
+pub struct VisibilityPolygon {
+    origin: Vec2,
+    occluders: Vec<(Entity, Vec2)>,
+    outline: Vec<Vec2>,
+}
+
+impl VisibilityPolygon {
+    pub fn compute(
+        field_of_view: &FieldOfView,
+        fov_transform: &Transform,
+        occluders: Vec<(Entity, Vec2)>,
+    ) -> Self {
+        let origin = fov_transform.translation.truncate();
+        let rays =
+            ((2. * field_of_view.half_angle() * RAYS_PER_RADIAN).ceil() as usize).max(MIN_RAYS);
+        let mut outline = vec![Vec2::ZERO];
+        for ray in 0..=rays {
+            let angle = -field_of_view.half_angle()
+                + 2. * field_of_view.half_angle() * ray as f32 / rays as f32;
+            // local space of the field of view looks along the y axis
+            let local_direction = Vec2::new(angle.sin(), angle.cos());
+            let direction = (fov_transform.rotation * local_direction.extend(0.)).truncate();
+            let distance = occluders
+                .iter()
+                .filter_map(|(_, center)| {
+                    ray_circle_distance(origin, direction, *center, ENTITY_RADIUS)
+                })
+                .fold(field_of_view.height(), f32::min);
+            outline.push(local_direction * distance);
+        }
+        VisibilityPolygon {
+            origin,
+            occluders,
+            outline,
+        }
+    }
+
+    pub fn unoccluded(field_of_view: &FieldOfView) -> Self {
+        VisibilityPolygon::compute(field_of_view, &Transform::identity(), vec![])
+    }
+
+    // Visible if no other entity crosses the line between the player and the center of the target
+    pub fn is_visible(&self, target: Entity, position: Vec2) -> bool {
+        let to_target = position - self.origin;
+        let distance = to_target.length();
+        if distance < f32::EPSILON {
+            return true;
+        }
+        let direction = to_target / distance;
+        !self
+            .occluders
+            .iter()
+            .filter(|(entity, _)| *entity != target)
+            .filter_map(|(_, center)| {
+                ray_circle_distance(self.origin, direction, *center, ENTITY_RADIUS)
+            })
+            .any(|hit| hit < distance - ENTITY_RADIUS)
+    }
+
+    pub fn outline(&self) -> &[Vec2] {
+        &self.outline
+    }
+}
+
+// Distance along the ray to the first intersection with the circle, if the ray hits it from outside
+fn ray_circle_distance(origin: Vec2, direction: Vec2, center: Vec2, radius: f32) -> Option<f32> {
+    let to_center = center - origin;
+    let projection = to_center.dot(direction);
+    let closest_squared = to_center.length_squared() - projection * projection;
+    if closest_squared > radius * radius {
+        return None;
+    }
+    let hit = projection - (radius * radius - closest_squared).sqrt();
+    if hit < 0. {
+        None
+    } else {
+        Some(hit)
+    }
+}
+
+fn compute_visibility(
+    mut visibility: ResMut<VisibilityPolygon>,
+    spatial_index: Res<SpatialIndex>,
+    tuning: Res<Tuning>,
+    field_of_view: Query<(&Transform, &FieldOfView)>,
+    entities: Query<&Transform, With<GameEntity>>,
+) {
+    let (fov_transform, field_of_view) = if let Ok(field_of_view) = field_of_view.single() {
+        field_of_view
+    } else {
+        return;
+    };
+    // enemies use the same occluders to find out whether they can see the player
+    let reach = field_of_view.height().max(tuning.steering.hunt_distance);
+    let occluders = spatial_index
+        .query_circle(fov_transform.translation.truncate(), reach + ENTITY_RADIUS)
+        .filter_map(|entity| {
+            entities
+                .get(entity)
+                .ok()
+                .map(|transform| (entity, transform.translation.truncate()))
+        })
+        .collect();
+    *visibility = VisibilityPolygon::compute(field_of_view, fov_transform, occluders);
+}
@@ -0,0 +1,108 @@
+// Builds without the window, GPU and audio backends: `cargo test --no-default-features`
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use game_plugin::{
+    Actions, ClockSystemLabels, GameClock, GameEntity, GameRng, HeadlessGamePlugin, Player,
+    PlayerState,
+};
+
+const FIXED_DELTA: Duration = Duration::from_millis(16);
+
+fn headless_app() -> App {
+    let mut app = App::build();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(HeadlessGamePlugin);
+    app.app
+}
+
+fn seeded_headless_app(seed: u64) -> App {
+    let mut app = App::build();
+    app.insert_resource(GameRng::from_seed(seed))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(HeadlessGamePlugin);
+    app.app
+}
+
+// Replaces the real frame time, so the game advances by the same amount every update
+fn fixed_step_headless_app() -> App {
+    let mut app = App::build();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(HeadlessGamePlugin)
+        .add_system_to_stage(
+            CoreStage::PreUpdate,
+            fixed_game_clock.system().after(ClockSystemLabels::Tick),
+        );
+    app.app
+}
+
+fn fixed_game_clock(mut clock: ResMut<GameClock>) {
+    clock.set_delta(FIXED_DELTA);
+}
+
+fn entity_positions(app: &mut App) -> Vec<Vec3> {
+    let mut query = app.world.query::<(&Transform, &GameEntity)>();
+    query
+        .iter(&app.world)
+        .map(|(transform, _)| transform.translation)
+        .collect()
+}
+
+#[test]
+fn spawns_player_and_entities() {
+    let mut app = headless_app();
+    app.update();
+
+    let mut player_query = app.world.query::<&Player>();
+    assert_eq!(player_query.iter(&app.world).count(), 1);
+    assert_eq!(entity_positions(&mut app).len(), 30);
+
+    let player_state = app.world.get_resource::<PlayerState>().unwrap();
+    assert!(!player_state.dead);
+    assert_eq!(player_state.level, 0);
+}
+
+#[test]
+fn entities_move_between_ticks() {
+    let mut app = fixed_step_headless_app();
+    app.update();
+    let before = entity_positions(&mut app);
+
+    for _ in 0..5 {
+        app.update();
+    }
+
+    let clock = app.world.get_resource::<GameClock>().unwrap();
+    assert_eq!(clock.elapsed(), FIXED_DELTA * 6);
+    let after = entity_positions(&mut app);
+    assert_eq!(before.len(), after.len());
+    assert!(before
+        .iter()
+        .zip(after.iter())
+        .any(|(before, after)| before != after));
+}
+
+#[test]
+fn same_seed_spawns_same_world() {
+    let mut first = seeded_headless_app(42);
+    let mut second = seeded_headless_app(42);
+    first.update();
+    second.update();
+
+    assert_eq!(entity_positions(&mut first), entity_positions(&mut second));
+}
+
+#[test]
+fn player_turns_towards_aim_direction() {
+    let mut app = headless_app();
+    app.update();
+    app.world
+        .get_resource_mut::<Actions>()
+        .unwrap()
+        .aim_direction = Vec2::new(1., 0.);
+    app.update();
+
+    let mut player_query = app.world.query::<(&Transform, &Player)>();
+    let (transform, _) = player_query.iter(&app.world).next().unwrap();
+    let facing = transform.rotation * Vec3::Y;
+    assert!((facing - Vec3::X).length() < 1e-4, "facing {:?}", facing);
+}